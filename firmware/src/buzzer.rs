@@ -0,0 +1,79 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! The piezo buzzer (BZ1), for feedback you don't have to look at.
+//!
+//! It's a bare piezo, so rather than just switching it on it needs a
+//! square wave somewhere near its resonant frequency, which LEDC makes
+//! for us. What to play and when is up to the sequencer in
+//! `scale_logic::buzzer`; this is just the PWM underneath it.
+
+use alloc::boxed::Box;
+
+use esp_hal::gpio::{interconnect::PeripheralOutput, DriveMode};
+use esp_hal::ledc::channel::{self, Channel, ChannelIFace};
+use esp_hal::ledc::timer::{self, Timer, TimerIFace};
+use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};
+use esp_hal::peripherals::LEDC;
+use esp_hal::time::Rate;
+
+use scale_logic::buzzer::{Pitch, Pwm};
+
+const TIMERS: [timer::Number; 4] = [
+    timer::Number::Timer0,
+    timer::Number::Timer1,
+    timer::Number::Timer2,
+    timer::Number::Timer3,
+];
+
+pub struct LedcBuzzer {
+    // one per pitch, always running; a channel can't retune the timer
+    // it's borrowing, but it can switch to another
+    timers: &'static [Timer<'static, LowSpeed>; 4],
+    channel: Channel<'static, LowSpeed>,
+}
+
+impl LedcBuzzer {
+    pub fn new(ledc: LEDC<'static>, pin: impl PeripheralOutput<'static>) -> LedcBuzzer {
+        // the channel borrows the timers, which borrow the controller,
+        // and all of them live as long as the firmware does anyway
+        let ledc = Box::leak(Box::new(Ledc::new(ledc)));
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+        let ledc: &'static Ledc<'static> = ledc;
+        let timers = Box::leak(Box::new(TIMERS.map(|number| ledc.timer::<LowSpeed>(number))));
+        for (timer, pitch) in timers.iter_mut().zip(Pitch::ALL) {
+            timer
+                .configure(timer::config::Config {
+                    duty: timer::config::Duty::Duty10Bit,
+                    clock_source: timer::LSClockSource::APBClk,
+                    frequency: Rate::from_hz(pitch.hz()),
+                })
+                .unwrap();
+        }
+        let timers: &'static [Timer<'static, LowSpeed>; 4] = timers;
+        let mut channel = ledc.channel(channel::Number::Channel0, pin);
+        channel
+            .configure(channel::config::Config {
+                timer: &timers[0],
+                duty_pct: 0,
+                drive_mode: DriveMode::PushPull,
+            })
+            .unwrap();
+        LedcBuzzer { timers, channel }
+    }
+}
+
+impl Pwm for LedcBuzzer {
+    fn tone(&mut self, pitch: Pitch, duty_pct: u8) {
+        let idx = Pitch::ALL.iter().position(|p| *p == pitch).unwrap_or(0);
+        let _ = self.channel.configure(channel::config::Config {
+            timer: &self.timers[idx],
+            duty_pct,
+            drive_mode: DriveMode::PushPull,
+        });
+    }
+
+    fn quiet(&mut self) {
+        let _ = self.channel.set_duty(0);
+    }
+}
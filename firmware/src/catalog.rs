@@ -0,0 +1,50 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! The rows of the recipe list, going by the recipes' tags and
+//! favorites and which ones were opened lately.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use scale_logic::catalog::{self, Kind, Meta, Row};
+use slint::Model;
+
+use crate::{Recipe, RecipeEntry, RecipeEntryKind};
+
+/// The rows for a folder, or for the top with "".
+pub fn rows(recipes: &impl Model<Data = Recipe>, recent: &[String], folder: &str) -> Vec<Row> {
+    let recipes: Vec<Recipe> = recipes.iter().collect();
+    let metas: Vec<Meta> = recipes.iter()
+        .map(|recipe| Meta { name: &recipe.name, tags: &recipe.tags, favorite: recipe.favorite })
+        .collect();
+    catalog::rows(&metas, recent, folder)
+}
+
+pub fn entries(rows: &[Row], recipes: &impl Model<Data = Recipe>) -> Vec<RecipeEntry> {
+    rows.iter()
+        .map(|row| RecipeEntry {
+            kind: match row.kind {
+                Kind::Heading => RecipeEntryKind::Heading,
+                Kind::Folder => RecipeEntryKind::Folder,
+                Kind::Back => RecipeEntryKind::Back,
+                Kind::Recipe => RecipeEntryKind::Recipe,
+            },
+            label: match row.kind {
+                Kind::Recipe => recipes.row_data(row.recipe).map(|recipe| recipe.name).unwrap_or_default(),
+                _ => row.label.as_str().into(),
+            },
+            recipe: row.recipe as i32,
+            letter: row.letter.map(|letter| String::from(letter).into()).unwrap_or_default(),
+            count: row.count as i32,
+        })
+        .collect()
+}
+
+/// The row to select once a folder's open: where we came out of if
+/// we're back at the top, otherwise the first one.
+pub fn landing(rows: &[Row], came_from: &str) -> i32 {
+    rows.iter()
+        .position(|row| row.kind == Kind::Folder && row.label == came_from)
+        .map_or_else(|| catalog::skip(rows, -1, 1), |row| row as i32)
+}
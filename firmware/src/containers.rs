@@ -0,0 +1,62 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! The bowls and tins we keep weighing things in, with what each one
+//! weighs empty.
+
+use alloc::vec::Vec;
+
+use embedded_storage::Storage;
+
+use crate::store::{Blob, Decoder, Encoder};
+use crate::Container;
+
+const VERSION: u32 = 1;
+
+fn encode(containers: &[Container]) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.u32(containers.len() as u32);
+    for container in containers {
+        encoder.str(&container.name);
+        encoder.f32(container.tare);
+    }
+    encoder.finish()
+}
+
+fn decode(data: &[u8]) -> Option<Vec<Container>> {
+    let mut decoder = Decoder::new(data);
+    if decoder.u32()? != VERSION {
+        return None;
+    }
+    let count = decoder.u32()?;
+    (0..count)
+        .map(|_| Some(Container { name: decoder.str()?.into(), tare: decoder.f32()? }))
+        .collect()
+}
+
+/// Loads the saved containers, or none if there aren't any.
+pub fn load<F: Storage>(blob: &Blob<F>) -> Vec<Container> {
+    blob.load().and_then(|data| decode(&data)).unwrap_or_default()
+}
+
+/// Why a new container can't go alongside the ones already saved, if
+/// there's a reason.
+pub fn check(containers: &[Container], container: &Container) -> Result<(), &'static str> {
+    if container.name.is_empty() {
+        return Err("it needs a name");
+    }
+    // remove would only ever get the first of two
+    if containers.iter().any(|other| other.name == container.name) {
+        return Err("there's already one with that name");
+    }
+    // which also keeps out nan and inf
+    if !(container.tare > 0. && container.tare.is_finite()) {
+        return Err("it should weigh more than 0");
+    }
+    Ok(())
+}
+
+pub fn save<F: Storage>(blob: &Blob<F>, containers: &[Container]) -> Result<(), &'static str> {
+    blob.save(&encode(containers))
+}
@@ -0,0 +1,129 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Every finished session, logged to flash for good, to look back over
+//! on the scale or export over serial.
+
+use alloc::format;
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+use scale_logic::history::{Clock, Line, Session};
+use slint::{Model, ModelRc, VecModel};
+
+use crate::store::{Decoder, Encoder, Log};
+use crate::{IngredientKind, LoggedIngredient, LoggedSession, Recipe, RecipeProgress};
+
+const VERSION: u32 = 1;
+
+fn encode(session: &Session) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.str(&session.recipe);
+    encoder.f32(session.scale_factor);
+    encoder.u32(session.started);
+    encoder.u32(session.ended);
+    encoder.u8(session.clock_set as u8);
+    encoder.u32(session.lines.len() as u32);
+    for line in &session.lines {
+        encoder.str(&line.name);
+        encoder.str(&line.unit);
+        encoder.f32(line.target);
+        encoder.f32(line.actual);
+    }
+    encoder.finish()
+}
+
+fn decode(data: &[u8]) -> Option<Session> {
+    let mut decoder = Decoder::new(data);
+    if decoder.u32()? != VERSION {
+        return None;
+    }
+    let recipe = decoder.str()?.into();
+    let scale_factor = decoder.f32()?;
+    let started = decoder.u32()?;
+    let ended = decoder.u32()?;
+    let clock_set = decoder.u8()? != 0;
+    let lines = (0..decoder.u32()?)
+        .map(|_| Some(Line {
+            name: decoder.str()?.into(),
+            unit: decoder.str()?.into(),
+            target: decoder.f32()?,
+            actual: decoder.f32()?,
+        }))
+        .collect::<Option<_>>()?;
+    Some(Session { recipe, scale_factor, started, ended, clock_set, lines })
+}
+
+/// Everything in the log, oldest first, read as it goes.
+pub fn sessions<F: NorFlash>(log: &Log<F>) -> impl Iterator<Item = Session> + '_ {
+    log.records().filter_map(|data| decode(&data))
+}
+
+/// Everything in the log, oldest first.
+pub fn load<F: NorFlash>(log: &Log<F>) -> Vec<Session> {
+    sessions(log).collect()
+}
+
+/// How a session went, ending now.
+pub fn session(recipe: &Recipe, progress: &RecipeProgress, clock: &Clock, now_ms: u64) -> Session {
+    let lines = recipe.ingredients.iter()
+        .zip(progress.ingredient_progresses.iter())
+        .map(|(ingredient, ingredient_progress)| {
+            let target = ingredient.amount * progress.scale_factor;
+            let (unit, target, actual) = match ingredient.kind {
+                IngredientKind::Weighed => ("g", target * 1000., ingredient_progress.amount * 1000.),
+                kind => {
+                    let unit = match kind {
+                        IngredientKind::Teaspoons => "tsp",
+                        IngredientKind::Tablespoons => "tbsp",
+                        IngredientKind::Cups => "cup",
+                        _ => "",
+                    };
+                    (unit, target, if ingredient_progress.done { target } else { 0. })
+                },
+            };
+            Line { name: ingredient.name.as_str().into(), unit: unit.into(), target, actual }
+        })
+        .collect();
+    Session {
+        recipe: recipe.name.as_str().into(),
+        scale_factor: progress.scale_factor,
+        started: progress.started.max(0) as u32,
+        ended: clock.now(now_ms),
+        clock_set: clock.is_set(),
+        lines,
+    }
+}
+
+pub fn save<F: NorFlash>(log: &mut Log<F>, session: &Session) -> Result<(), &'static str> {
+    log.append(&encode(session))
+}
+
+/// For showing on the scale, newest first.
+pub fn rows(sessions: &[Session], clock: &Clock) -> Vec<LoggedSession> {
+    sessions.iter()
+        .rev()
+        .map(|session| LoggedSession {
+            recipe: session.recipe.as_str().into(),
+            scale_factor: session.scale_factor,
+            when: if session.clock_set {
+                clock.date_text(session.ended).into()
+            } else {
+                format!("{}s after a boot", session.ended).into()
+            },
+            seconds: session.seconds().map_or(-1, |seconds| seconds as i32),
+            worst_miss: session.worst_miss(),
+            ingredients: ModelRc::new(VecModel::from(
+                session.lines.iter()
+                    .map(|line| LoggedIngredient {
+                        name: line.name.as_str().into(),
+                        unit: line.unit.as_str().into(),
+                        target: line.target,
+                        actual: line.actual,
+                    })
+                    .collect::<Vec<_>>()
+            )),
+        })
+        .collect()
+}
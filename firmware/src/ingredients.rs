@@ -0,0 +1,257 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! The ingredient database: what recipes' ingredients really are, so
+//! they can be weighed from a volume, added up into nutrition, and
+//! swapped for something else.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use embedded_storage::Storage;
+use scale_logic::ingredients::{self as db, Entry, Substitute, EGGS, GLUTEN, MILK, SOY};
+use slint::{Model, ModelRc, SharedString};
+
+use crate::recipes;
+use crate::store::{Blob, Decoder, Encoder};
+use crate::{Ingredient, IngredientKind, IngredientProgress, Nutrition, Recipe};
+
+// Only the user's own entries get saved; the built-in ones always come
+// from `defaults`, so they can be fixed in new firmware. The first
+// version saved the whole database, built-ins and all.
+const VERSION: u32 = 2;
+const EVERYTHING_VERSION: u32 = 1;
+
+// kcal, protein, fat, carbs
+fn entry(
+    name: &str,
+    aliases: &[&str],
+    density: f32,
+    allergens: u16,
+    [kcal, protein, fat, carbs]: [f32; 4],
+    substitutes: &[(&str, f32)],
+) -> Entry {
+    Entry {
+        name: name.into(),
+        aliases: aliases.iter().map(|&alias| alias.into()).collect(),
+        density,
+        allergens,
+        per_100g: db::Nutrition { kcal, protein, fat, carbs },
+        substitutes: substitutes.iter().map(|&(name, ratio)| Substitute { name: name.into(), ratio }).collect(),
+    }
+}
+
+/// What's built in, which covers everything in the built-in recipes.
+pub fn defaults() -> Vec<Entry> {
+    vec![
+        entry("all-purpose flour", &["flour", "APF", "plain flour"], 0.53, GLUTEN, [364., 10.3, 1.0, 76.3],
+              &[("bread flour", 1.)]),
+        entry("low-protein flour", &["low-protein APF", "pastry flour"], 0.5, GLUTEN, [364., 8., 1., 78.],
+              &[("all-purpose flour", 1.)]),
+        entry("bread flour", &[], 0.55, GLUTEN, [361., 12., 1.7, 72.5], &[("all-purpose flour", 1.)]),
+        entry("water", &["cold tap water"], 1., 0, [0., 0., 0., 0.], &[]),
+        entry("granulated sugar", &["sugar", "white sugar"], 0.85, 0, [387., 0., 0., 100.],
+              &[("light brown sugar", 1.)]),
+        entry("light brown sugar", &["brown sugar"], 0.93, 0, [380., 0.1, 0., 98.], &[("granulated sugar", 1.)]),
+        entry("salt", &["table salt", "fine salt"], 1.2, 0, [0., 0., 0., 0.], &[("kosher salt", 1.)]),
+        entry("instant yeast", &["yeast"], 0.64, 0, [325., 40.4, 7.6, 41.2], &[("active dry yeast", 1.25)]),
+        entry("unsalted butter", &["butter"], 0.96, MILK, [717., 0.9, 81.1, 0.1],
+              &[("salted butter", 1.), ("vegan butter", 1.)]),
+        entry("salted butter", &[], 0.96, MILK, [717., 0.9, 81.1, 0.1], &[("unsalted butter", 1.)]),
+        entry("vegan butter", &["margarine", "plant butter"], 0.96, 0, [700., 0., 79., 0.],
+              &[("unsalted butter", 1.)]),
+        entry("olive oil", &["extra virgin olive oil"], 0.91, 0, [884., 0., 100., 0.], &[("vegetable oil", 1.)]),
+        entry("whole milk", &["milk"], 1.03, MILK, [61., 3.2, 3.3, 4.8], &[("soy milk", 1.)]),
+        entry("soy milk", &[], 1.03, SOY, [54., 3.3, 1.8, 6.3], &[("whole milk", 1.)]),
+        entry("sweetened condensed milk", &["condensed milk"], 1.3, MILK, [321., 7.9, 8.7, 54.4], &[]),
+        entry("eggs", &["egg", "whole eggs"], 1.03, EGGS, [143., 12.6, 9.5, 0.7], &[("Just Egg", 1.)]),
+        entry("egg yolks", &["egg yolk"], 1.03, EGGS, [322., 15.9, 26.5, 3.6], &[]),
+        entry("Just Egg", &[], 1., 0, [160., 11.4, 11.4, 2.3], &[("eggs", 1.)]),
+        entry("corn starch", &["cornstarch", "cornflour"], 0.54, 0, [381., 0.3, 0.1, 91.3], &[("arrowroot", 1.)]),
+        entry("vanilla extract", &[], 0.88, 0, [288., 0.1, 0.1, 12.7], &[("vanilla bean paste", 1.)]),
+        // the pods themselves, from their usual makeup, since there's no
+        // USDA row for them
+        entry("vanilla bean", &[], 0., 0, [290., 3.5, 11., 45.], &[("vanilla extract", 3.)]),
+        entry("ground cinnamon", &["cinnamon"], 0.56, 0, [247., 4., 1.2, 80.6], &[]),
+        entry("ground ginger", &["ginger"], 0.45, 0, [335., 9., 4.2, 71.6], &[]),
+        entry("ground cloves", &["cloves"], 0.5, 0, [274., 6., 13., 65.5], &[("ground allspice", 1.)]),
+        entry("grated nutmeg", &["nutmeg", "ground nutmeg"], 0.5, 0, [525., 5.8, 36.3, 49.3], &[("ground mace", 1.)]),
+        entry("butternut puree", &["butternut squash"], 1., 0, [45., 1., 0.1, 11.7], &[("pumpkin puree", 1.)]),
+    ]
+}
+
+fn encode(entries: &[Entry]) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.u32(entries.len() as u32);
+    for entry in entries {
+        encoder.str(&entry.name);
+        encoder.u32(entry.aliases.len() as u32);
+        for alias in &entry.aliases {
+            encoder.str(alias);
+        }
+        encoder.f32(entry.density);
+        encoder.u32(entry.allergens as u32);
+        encoder.f32(entry.per_100g.kcal);
+        encoder.f32(entry.per_100g.protein);
+        encoder.f32(entry.per_100g.fat);
+        encoder.f32(entry.per_100g.carbs);
+        encoder.u32(entry.substitutes.len() as u32);
+        for substitute in &entry.substitutes {
+            encoder.str(&substitute.name);
+            encoder.f32(substitute.ratio);
+        }
+    }
+    encoder.finish()
+}
+
+// the entries, and which version they were saved by
+fn decode(data: &[u8]) -> Option<(u32, Vec<Entry>)> {
+    let mut decoder = Decoder::new(data);
+    let version = decoder.u32()?;
+    if version != VERSION && version != EVERYTHING_VERSION {
+        return None;
+    }
+    let count = decoder.u32()?;
+    let entries = (0..count)
+        .map(|_| {
+            let name = decoder.str()?.into();
+            let aliases = (0..decoder.u32()?).map(|_| Some(decoder.str()?.into())).collect::<Option<_>>()?;
+            let density = decoder.f32()?;
+            let allergens = decoder.u32()? as u16;
+            let per_100g = db::Nutrition {
+                kcal: decoder.f32()?,
+                protein: decoder.f32()?,
+                fat: decoder.f32()?,
+                carbs: decoder.f32()?,
+            };
+            let substitutes = (0..decoder.u32()?)
+                .map(|_| Some(Substitute { name: decoder.str()?.into(), ratio: decoder.f32()? }))
+                .collect::<Option<_>>()?;
+            Some(Entry { name, aliases, density, allergens, per_100g, substitutes })
+        })
+        .collect::<Option<_>>()?;
+    Some((version, entries))
+}
+
+/// Loads the database: the built-in entries, plus the user's own from
+/// flash.
+pub fn load<F: Storage>(blob: &Blob<F>) -> Vec<Entry> {
+    let own = match blob.load().and_then(|data| decode(&data)) {
+        Some((VERSION, own)) => own,
+        Some((_, everything)) => {
+            // only ones that aren't built in can be the user's, and an old
+            // copy of a built-in one would hide any fixes to it. If this
+            // doesn't get saved, it just happens again next time.
+            let defaults = defaults();
+            let own: Vec<Entry> = everything.into_iter()
+                .filter(|entry| db::get(&defaults, &entry.name).is_none())
+                .collect();
+            let _ = save(blob, &own);
+            own
+        },
+        None => Vec::new(),
+    };
+    db::merge(defaults(), own)
+}
+
+/// Saves the user's own entries, leaving out the built-in ones.
+pub fn save<F: Storage>(blob: &Blob<F>, own: &[Entry]) -> Result<(), &'static str> {
+    blob.save(&encode(own))
+}
+
+/// The entry an ingredient with this name would be, if any.
+pub fn link(entries: &[Entry], name: &str) -> SharedString {
+    db::find(entries, name).map_or_else(SharedString::new, |entry| entry.name.as_str().into())
+}
+
+/// Links any ingredients that aren't linked yet to whatever entries
+/// their names match, e.g. for recipes saved before there was a
+/// database.
+pub fn link_recipes(entries: &[Entry], recipes: &[Recipe]) {
+    for recipe in recipes {
+        let Some(ingredients) = recipes::editable_ingredients(recipe) else {
+            continue;
+        };
+        for idx in 0..ingredients.row_count() {
+            let Some(ingredient) = ingredients.row_data(idx) else {
+                continue;
+            };
+            if !ingredient.entry.is_empty() {
+                continue;
+            }
+            let entry = link(entries, &ingredient.name);
+            if !entry.is_empty() {
+                ingredients.set_row_data(idx, Ingredient { entry, ..ingredient });
+            }
+        }
+    }
+}
+
+fn millilitres(kind: IngredientKind, amount: f32) -> Option<f32> {
+    match kind {
+        IngredientKind::Teaspoons => Some(amount * db::ML_PER_TEASPOON),
+        IngredientKind::Tablespoons => Some(amount * db::ML_PER_TABLESPOON),
+        IngredientKind::Cups => Some(amount * db::ML_PER_CUP),
+        IngredientKind::Weighed | IngredientKind::Counted => None,
+    }
+}
+
+/// What a measured ingredient weighs, in kg, or 0 if we don't know.
+pub fn weight(entries: &[Entry], ingredient: &Ingredient) -> f32 {
+    let grams = millilitres(ingredient.kind, ingredient.amount)
+        .and_then(|ml| db::get(entries, &ingredient.entry)?.grams(ml));
+    grams.unwrap_or(0.) / 1000.
+}
+
+/// What's in everything done so far: what was weighed out, or what
+/// was measured for the rest. Counted ones and ones we don't know
+/// about don't add anything.
+pub fn nutrition(
+    entries: &[Entry],
+    ingredients: &ModelRc<Ingredient>,
+    progresses: &ModelRc<IngredientProgress>,
+) -> Nutrition {
+    let mut total = db::Nutrition::default();
+    for (ingredient, progress) in ingredients.iter().zip(progresses.iter()) {
+        let Some(entry) = db::get(entries, &ingredient.entry).filter(|_| progress.done) else {
+            continue;
+        };
+        let grams = match ingredient.kind {
+            IngredientKind::Weighed => progress.amount * 1000.,
+            _ => weight(entries, &ingredient) * 1000.,
+        };
+        total.add(&entry.per_100g, grams);
+    }
+    Nutrition { kcal: total.kcal, protein: total.protein, fat: total.fat, carbs: total.carbs }
+}
+
+/// Every allergen in any of them, as a list.
+pub fn allergens(entries: &[Entry], ingredients: &ModelRc<Ingredient>) -> SharedString {
+    let flags = ingredients.iter()
+        .filter_map(|ingredient| db::get(entries, &ingredient.entry))
+        .fold(0, |flags, entry| flags | entry.allergens);
+    db::allergen_names(flags).collect::<Vec<_>>().join(", ").into()
+}
+
+/// What would do instead, with how much for the weighed ones.
+pub fn substitutes(entries: &[Entry], ingredient: &Ingredient) -> SharedString {
+    let Some(entry) = db::get(entries, &ingredient.entry) else {
+        return SharedString::new();
+    };
+    let names: Vec<String> = entry.substitutes.iter()
+        .map(|substitute| match ingredient.kind {
+            IngredientKind::Weighed => {
+                format!("{}g {}", (ingredient.amount * substitute.ratio * 1000. + 0.5) as u32, substitute.name)
+            },
+            _ => substitute.name.clone(),
+        })
+        .collect();
+    if names.is_empty() {
+        SharedString::new()
+    } else {
+        format!("or {}", names.join(", ")).into()
+    }
+}
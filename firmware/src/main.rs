@@ -6,17 +6,15 @@
 
 extern crate alloc;
 
-use core::cell::RefCell;
-use core::iter;
-use core::mem;
+use core::cell::{Cell, RefCell};
 use core::slice;
 
 use alloc::boxed::Box;
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use critical_section::Mutex;
-use embedded_hal_1::delay::DelayNs;
 use embedded_hal_1::digital::InputPin;
 use embedded_hal_1::i2c::I2c as I2cTrait;
 use esp_backtrace as _;
@@ -29,23 +27,52 @@ use esp_hal::{
     i2c::master::{I2c, Config as I2cConfig},
     spi::{Mode as SpiMode, master::{Spi, Config as SpiConfig}},
     time::{self, Rate},
+    usb_serial_jtag::UsbSerialJtag,
     handler,
 };
 
-use nau7802::AfeCalibrationStatus;
 use rotary_encoder_hal::{Direction, Rotary, DefaultPhase};
-use nau7802::Nau7802;
 use heapless::spsc::{Queue, Producer};
 use debouncr::{DebouncerStateful, Edge, Repeat6, debounce_stateful_6};
 
 use slint::platform::software_renderer::RenderingRotation;
 use slint::platform::software_renderer::{MinimalSoftwareWindow, Rgb565Pixel, TargetPixel, PremultipliedRgbaColor};
 use slint::platform::{software_renderer as renderer, Platform, WindowEvent, Key};
-use slint::{Model, PhysicalSize};
-
+use slint::{Model, PhysicalSize, SharedString, VecModel};
+
+use embedded_storage::Storage;
+use esp_storage::FlashStorage;
+use scale_logic::buzzer::{self as sounds, Sequencer};
+use scale_logic::containers::{self as spotting, Change, Watcher};
+use scale_logic::counting::{self, Counter};
+use scale_logic::history::Clock;
+use scale_logic::portions;
+use scale_logic::rebalance;
+use scale_logic::serial::{Command, LineReader};
+use scale_logic::stream::{Sample, Stream, StreamFormat};
+use scale_logic::timers::{Timer, Timers};
+use scale_logic::tolerance::{self, Band};
+use scale_logic::trend::{self, Trend};
+use scale_logic::wheel as acceleration;
 use t_display_s3_amoled::rm67162::dma::RM67162Dma;
 use t_display_s3_amoled::rm67162::Orientation;
 
+mod buzzer;
+mod catalog;
+mod containers;
+mod history;
+mod ingredients;
+mod pantry;
+mod recipes;
+mod scale;
+mod sessions;
+mod settings;
+mod store;
+
+use buzzer::LedcBuzzer;
+use scale::{Reading, Scale};
+use store::{Blob, Journal, Log};
+
 #[derive(Debug, Clone, Copy)]
 enum ButtonEvent {
     Press,
@@ -96,8 +123,9 @@ impl<P: InputPin> Button<P> {
 
 #[derive(Debug, Clone, Copy)]
 enum Event {
-    WheelCW,
-    WheelCCW,
+    // when, in µs, so fast spins can go further
+    WheelCW(u64),
+    WheelCCW(u64),
     WheelButton(ButtonEvent),
     BackButton(ButtonEvent),
 }
@@ -118,8 +146,8 @@ fn gpio_handler() {
         let mut borrowed_resources = INTERRUPT_RESOURCES.borrow_ref_mut(cs);
         let resources = borrowed_resources.as_mut().unwrap();
         match resources.encoder.update().unwrap() {
-            Direction::Clockwise => { let _ = resources.producer.enqueue(Event::WheelCW); },
-            Direction::CounterClockwise => { let _ = resources.producer.enqueue(Event::WheelCCW); },
+            Direction::Clockwise => { let _ = resources.producer.enqueue(Event::WheelCW(now_us())); },
+            Direction::CounterClockwise => { let _ = resources.producer.enqueue(Event::WheelCCW(now_us())); },
             Direction::None => { },
         }
         let (pin_a, pin_b) = resources.encoder.pins();
@@ -147,8 +175,6 @@ fn timer0_handler() {
     });
 }
 
-const ONE_KG: f32 = 1.0 / 674500.0;
-
 slint::include_modules!();
 
 struct Backend {
@@ -231,233 +257,6 @@ fn cast_pixel_buffer(b: &[Rgb565PixelFlipped]) -> &[u8] {
     unsafe { slice::from_raw_parts(b.as_ptr() as *const u8, b.len() * 2) }
 }
 
-#[derive(Default)]
-enum Scale<I: I2cTrait> {
-    #[default]
-    Empty,
-    Unconnected(I),
-    Calibrating(Nau7802<I>),
-    Running { adc: Nau7802<I>, zero: i32, val: i32 },
-}
-
-impl<I: I2cTrait> Scale<I> {
-    fn step_inner(self, wait: &mut impl DelayNs) -> (Self, Option<f32>) {
-        use Scale::*;
-        match self {
-            Empty =>
-                // this shouldn't happen :)
-                (Empty, None),
-            Unconnected(i2c) =>
-                match Nau7802::new(i2c, wait) {
-                    Ok(adc) =>
-                        (Calibrating(adc), None),
-                    Err((_, i2c)) =>
-                        (Unconnected(i2c), None),
-                },
-            Calibrating(mut adc) =>
-                if let Ok(AfeCalibrationStatus::Success) = adc.poll_afe_calibration_status() {
-                    if let Ok(val) = adc.read() {
-                        (Running { adc, zero: val, val }, Some(0.))
-                    } else {
-                        (Calibrating(adc), None)
-                    }
-                } else {
-                    (Calibrating(adc), None)
-                },
-            Running { mut adc, zero, val } => {
-                let new_val = adc.read().unwrap_or(val);
-                (Running { adc, zero, val: new_val }, Some(((new_val - zero) as f32) * ONE_KG))
-            },
-        }
-    }
-
-    /// Steps the connecting/calibrating/running state
-    /// machine. Returns the most recent reading if we have calibrated
-    /// successfully.
-    fn step(&mut self, wait: &mut impl DelayNs) -> Option<f32> {
-        let real_self = mem::take(self);
-        let (new_self, result) = real_self.step_inner(wait);
-        let _ = mem::replace(self, new_self);
-        result
-    }
-
-    fn rezero(&mut self) {
-        if let &mut Scale::Running { ref mut zero, val, .. } = self {
-            *zero = val;
-        }
-    }
-}
-
-fn ingredient(name: &str, amount: f32) -> Ingredient {
-    Ingredient { name: name.into(), amount }
-}
-
-fn vegan_choux() -> Recipe {
-    Recipe {
-        name: "Vegan Choux".into(),
-        ingredients: [
-            ingredient("water", 0.06),
-            ingredient("soy milk", 0.06),
-            ingredient("vanilla extract", 0.005),
-            ingredient("sugar", 0.006),
-            ingredient("vegan butter", 0.028),
-            ingredient("all-purpose flour", 0.065),
-            ingredient("Just Egg", 0.125),
-            ingredient("soy milk", 0.030),
-        ].into(),
-    }
-}
-
-fn vegan_creme_pat() -> Recipe {
-    Recipe {
-        name: "Vegan Creme Pat".into(),
-        ingredients: [
-            // ingredient("soy milk", 0.243),
-            // ingredient("vanilla extract", 0.010),
-            // ingredient("salt", 0.001),
-            // ingredient("corn starch", 0.016),
-            // ingredient("sugar", 0.050),
-            // ingredient("Just Egg", 0.083),
-            // ingredient("vegan butter", 0.042),
-            ingredient("soy milk", 0.486),
-            ingredient("vanilla extract", 0.020),
-            ingredient("salt", 0.002),
-            ingredient("corn starch", 0.032),
-            ingredient("sugar", 0.100),
-            ingredient("Just Egg", 0.166),
-            ingredient("vegan butter", 0.084)
-        ].into(),
-    }
-}
-
-fn choux() -> Recipe {
-    Recipe {
-        name: "Choux".into(),
-        ingredients: [
-            ingredient("water", 0.235),
-            ingredient("butter", 0.084),
-            ingredient("sugar", 0.008),
-            ingredient("salt", 0.002),
-            ingredient("all-purpose flour", 0.128),
-            ingredient("eggs", 0.200),
-        ].into(),
-    }
-}
-
-fn creme_pat() -> Recipe {
-    Recipe {
-        name: "Creme Pat".into(),
-        ingredients: [
-            ingredient("milk", 0.455),
-            ingredient("vanilla bean", 0.001),
-            ingredient("sugar", 0.115),
-            ingredient("corn starch", 0.030),
-            ingredient("salt", 0.001),
-            ingredient("egg yolks", 0.070),
-            ingredient("butter", 0.030),
-        ].into(),
-    }
-}
-
-fn pasta_dough() -> Recipe {
-    Recipe {
-        name: "Egg Pasta".into(),
-        ingredients: [
-            ingredient("flour", 0.255),
-            ingredient("whole eggs", 0.110),
-            ingredient("egg yolks", 0.070),
-            ingredient("salt", 0.003),
-        ].into(),
-    }
-}
-
-fn poolish_bread() -> Recipe {
-    Recipe {
-        name: "Poolish Bread".into(),
-        ingredients: [
-            ingredient("flour", 0.5),
-            ingredient("yeast", 0.0004),
-            ingredient("water (80F)", 0.5),
-            ingredient("flour", 0.5),
-            ingredient("salt", 0.021),
-            ingredient("yeast", 0.003),
-            ingredient("water (105F)", 0.25),
-        ].into(),
-    }
-}
-
-fn focaccia() -> Recipe {
-    Recipe {
-        name: "Focaccia".into(),
-        ingredients: [
-            ingredient("flour", 0.5),
-            ingredient("salt", 0.01),
-            ingredient("yeast", 0.004),
-            ingredient("water (roomtemp)", 0.4),
-            ingredient("olive oil", 0.02),
-            ingredient("olive oil", 0.028),
-            ingredient("olive oil", 0.02),
-        ].into(),
-    }
-}
-
-fn kouign_amann() -> Recipe {
-    Recipe {
-        name: "Kouign Amann".into(),
-        ingredients: [
-            ingredient("flour", 0.213),
-            ingredient("salt", 0.0032),
-            ingredient("yeast", 0.0016),
-            ingredient("water (75F)", 0.145),
-            ingredient("salted butter", 0.134),
-            ingredient("sugar", 0.156),
-        ].into(),
-    }
-}
-
-fn pie_dough() -> Recipe {
-    Recipe {
-        name: "Pie Dough".into(),
-        ingredients: [
-            ingredient("low-protein APF", 0.225),
-            ingredient("sugar", 0.015),
-            ingredient("salt", 0.004),
-            ingredient("unsalted butter", 0.225),
-            ingredient("cold tap water", 0.115),
-        ].into(),
-    }
-}
-
-fn butternut_pie() -> Recipe {
-    Recipe {
-        name: "Butternut Pie".into(),
-        ingredients: [
-            ingredient("butternut puree", 0.395),
-            ingredient("condensed milk", 0.680),
-            ingredient("light brown sugar", 0.115),
-            ingredient("vanilla extract", 0.015),
-            ingredient("3/2tsp ground ginger", 0.001),
-            ingredient("3/2tsp ground cinnamon", 0.001),
-            ingredient("1/4tsp grated nutmeg", 0.001),
-            ingredient("salt", 0.001),
-            ingredient("1/8tsp ground cloves", 0.001),
-            ingredient("unsalted butter", 0.030),
-            ingredient("eggs", 0.145),
-        ].into(),
-    }
-}
-
-fn progress_for_recipe(recipe: &Recipe) -> RecipeProgress {
-    RecipeProgress {
-        scale_factor: 1.0,
-        ingredient_progresses:
-            iter::repeat(IngredientProgress { done: false, amount: 0.0 })
-            .take(recipe.ingredients.row_count())
-            .collect::<Vec<_>>()[..]
-            .into(),
-    }
-}
-
 #[esp_hal::main]
 fn main() -> ! {
     // init_heap();
@@ -527,7 +326,15 @@ fn main() -> ! {
     let i2c = I2c::new(peripherals.I2C0, i2c_config).unwrap()
         .with_sda(peripherals.GPIO43)
         .with_scl(peripherals.GPIO44);
-    let scale = Rc::new(RefCell::new(Scale::Unconnected(i2c)));
+    let scale = Rc::new(RefCell::new(Scale::new(i2c)));
+
+    let buzzer = Rc::new(RefCell::new(Sequencer::new(LedcBuzzer::new(peripherals.LEDC, peripherals.GPIO11))));
+
+    // println! already writes to the USB serial port, so we only need
+    // the receiving half for commands
+    let (mut serial_rx, _serial_tx) = UsbSerialJtag::new(peripherals.USB_DEVICE).split();
+    let mut serial_line = LineReader::default();
+    let mut stream: Option<Stream> = None;
 
     println!("init display");
 
@@ -580,49 +387,532 @@ fn main() -> ! {
 
     let mut framebuf = [Rgb565PixelFlipped(0); 536*240];
 
+    // whether the container we're tared for was spotted by its weight,
+    // so it gets untared once it comes off again
+    let spotted = Rc::new(Cell::new(false));
+
     let scale_ref = scale.clone();
+    let ui_ref = ui.as_weak();
+    let spotted_ref = spotted.clone();
     ui.global::<ScaleControls>().on_zero(move || {
         scale_ref.borrow_mut().rezero();
+        // tared for whatever's on there now instead
+        if let Some(ui) = ui_ref.upgrade() {
+            ui.global::<ContainerStore>().set_current(SharedString::new());
+        }
+        spotted_ref.set(false);
+    });
+
+    // set while counting pieces, to refine what one weighs
+    let counter: Rc<RefCell<Option<Counter>>> = Rc::new(RefCell::new(None));
+    let piece_counter = ui.global::<PieceCounter>();
+    let counter_ref = counter.clone();
+    let ui_ref = ui.as_weak();
+    piece_counter.on_sample(move |weight, pieces| {
+        let new_counter = Counter::new(weight, pieces.max(1) as u32);
+        if let Some(ui) = ui_ref.upgrade() {
+            let piece_counter = ui.global::<PieceCounter>();
+            piece_counter.set_unit(new_counter.unit());
+            piece_counter.set_too_light(counting::too_light(new_counter.unit()));
+        }
+        *counter_ref.borrow_mut() = Some(new_counter);
+    });
+    let counter_ref = counter.clone();
+    let ui_ref = ui.as_weak();
+    piece_counter.on_stop(move || {
+        *counter_ref.borrow_mut() = None;
+        if let Some(ui) = ui_ref.upgrade() {
+            ui.global::<PieceCounter>().set_unit(0.);
+        }
+    });
+
+    // set while watching something reduce
+    let trend: Rc<RefCell<Option<Trend>>> = Rc::new(RefCell::new(None));
+    let weight_trend = ui.global::<WeightTrend>();
+    let trend_ref = trend.clone();
+    let ui_ref = ui.as_weak();
+    weight_trend.on_start(move || {
+        *trend_ref.borrow_mut() = Some(Trend::default());
+        if let Some(ui) = ui_ref.upgrade() {
+            ui.global::<WeightTrend>().set_rate_known(false);
+        }
+    });
+    let trend_ref = trend.clone();
+    weight_trend.on_stop(move || {
+        *trend_ref.borrow_mut() = None;
+    });
+    weight_trend.on_seconds_to(|current, target, rate| {
+        trend::seconds_to(current, target, rate).map_or(-1, |seconds| seconds as i32)
+    });
+
+    let buzzer_ref = buzzer.clone();
+    ui.global::<BuzzerControls>().on_play(move |sound| {
+        buzzer_ref.borrow_mut().play(sound_for(sound));
     });
 
-    let recipes = [
-        vegan_choux(),
-        vegan_creme_pat(),
-        choux(),
-        creme_pat(),
-        pasta_dough(),
-        poolish_bread(),
-        focaccia(),
-        kouign_amann(),
-        pie_dough(),
-        butternut_pie(),
-    ];
-    let progresses = recipes.iter().map(progress_for_recipe).collect::<Vec<_>>()[..].into();
-    ui.set_recipes(recipes.into());
-    ui.set_recipe_progresses(progresses);
-
-    let mut i = 0;
+    let timers = Rc::new(RefCell::new(Timers::<SharedString>::default()));
+    let timer_rows = Rc::new(VecModel::<KitchenTimer>::default());
+    let kitchen_timers = ui.global::<KitchenTimers>();
+    kitchen_timers.set_timers(timer_rows.clone().into());
+    let timers_ref = timers.clone();
+    let rows_ref = timer_rows.clone();
+    kitchen_timers.on_start(move |name, seconds| {
+        let mut timers = timers_ref.borrow_mut();
+        let id = timers.start(name, seconds.max(0) as u32, now_ms());
+        sync_timers(&timers, &rows_ref, now_ms());
+        id
+    });
+    let timers_ref = timers.clone();
+    let rows_ref = timer_rows.clone();
+    kitchen_timers.on_cancel(move |id| {
+        let mut timers = timers_ref.borrow_mut();
+        timers.cancel(id);
+        sync_timers(&timers, &rows_ref, now_ms());
+    });
+    kitchen_timers.on_index_of(|rows, id| {
+        rows.iter()
+            .position(|row| row.id == id)
+            .map_or(-1, |idx| idx as i32)
+    });
+
+    let flash = Rc::new(RefCell::new(FlashStorage::new(peripherals.FLASH)));
+    let recipe_blob = Blob::new(flash.clone(), store::RECIPES);
+    let settings_blob = Blob::new(flash.clone(), store::SETTINGS);
+
+    let settings_store = ui.global::<SettingsStore>();
+    let saved_settings = settings::load(&settings_blob);
+    apply_settings(&mut buzzer.borrow_mut(), &saved_settings);
+    settings_store.set_settings(saved_settings);
+    settings_store.set_max_volume(sounds::MAX_VOLUME as i32);
+    let buzzer_ref = buzzer.clone();
+    settings_store.on_apply(move |settings| {
+        let mut buzzer = buzzer_ref.borrow_mut();
+        apply_settings(&mut buzzer, &settings);
+        // so you can hear what you picked
+        buzzer.play(sounds::Sound::InRange);
+    });
+    let buzzer_ref = buzzer.clone();
+    settings_store.on_save(move |settings| {
+        if let Err(err) = settings::save(&settings_blob, &settings) {
+            println!("couldn't save settings: {}", err);
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        }
+    });
+
+    let container_blob = Rc::new(Blob::new(flash.clone(), store::CONTAINERS));
+    let containers = Rc::new(VecModel::from(containers::load(&container_blob)));
+    let container_store = ui.global::<ContainerStore>();
+    container_store.set_containers(containers.clone().into());
+    let scale_ref = scale.clone();
+    let ui_ref = ui.as_weak();
+    let containers_ref = containers.clone();
+    let spotted_ref = spotted.clone();
+    container_store.on_use(move |idx| {
+        let Some(ui) = ui_ref.upgrade() else {
+            return;
+        };
+        let container = usize::try_from(idx).ok().and_then(|idx| containers_ref.row_data(idx));
+        tare_for(&mut scale_ref.borrow_mut(), &ui.global::<ContainerStore>(), container);
+        spotted_ref.set(false);
+    });
+    let scale_ref = scale.clone();
+    let ui_ref = ui.as_weak();
+    let spotted_ref = spotted.clone();
+    container_store.on_reset_empty(move || {
+        scale_ref.borrow_mut().reset_empty();
+        if let Some(ui) = ui_ref.upgrade() {
+            ui.global::<ContainerStore>().set_current(SharedString::new());
+        }
+        spotted_ref.set(false);
+    });
+    let scale_ref = scale.clone();
+    let ui_ref = ui.as_weak();
+    let containers_ref = containers.clone();
+    let blob_ref = container_blob.clone();
+    let spotted_ref = spotted.clone();
+    let buzzer_ref = buzzer.clone();
+    container_store.on_add(move |name, tare| {
+        let container = Container { name, tare };
+        if !add_container(&blob_ref, &containers_ref, container.clone()) {
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        } else if let Some(ui) = ui_ref.upgrade() {
+            tare_for(&mut scale_ref.borrow_mut(), &ui.global::<ContainerStore>(), Some(container));
+        }
+        spotted_ref.set(false);
+    });
+    let containers_ref = containers.clone();
+    let blob_ref = container_blob.clone();
+    let buzzer_ref = buzzer.clone();
+    container_store.on_remove(move |idx| {
+        let Some(idx) = usize::try_from(idx).ok().filter(|&idx| idx < containers_ref.row_count()) else {
+            return;
+        };
+        containers_ref.remove(idx);
+        if !save_containers(&blob_ref, &containers_ref) {
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        }
+    });
+
+    let ingredient_blob = Blob::new(flash.clone(), store::INGREDIENTS);
+    let entries = Rc::new(ingredients::load(&ingredient_blob));
+    let ingredient_db = ui.global::<IngredientDb>();
+    let entries_ref = entries.clone();
+    ingredient_db.on_link(move |name| ingredients::link(&entries_ref, &name));
+    let entries_ref = entries.clone();
+    ingredient_db.on_weight(move |ingredient| ingredients::weight(&entries_ref, &ingredient));
+    let entries_ref = entries.clone();
+    ingredient_db.on_nutrition(move |ingredients, progresses| {
+        ingredients::nutrition(&entries_ref, &ingredients, &progresses)
+    });
+    let entries_ref = entries.clone();
+    ingredient_db.on_allergens(move |ingredients| ingredients::allergens(&entries_ref, &ingredients));
+    let entries_ref = entries.clone();
+    ingredient_db.on_substitutes(move |ingredient| ingredients::substitutes(&entries_ref, &ingredient));
+
+    let pantry_blob = Rc::new(Blob::new(flash.clone(), store::PANTRY));
+    let stock = Rc::new(RefCell::new(pantry::load(&pantry_blob)));
+    let stock_rows = Rc::new(VecModel::from(pantry::rows(&stock.borrow())));
+    let pantry_store = ui.global::<Pantry>();
+    pantry_store.set_stock(stock_rows.clone().into());
+    pantry_store.set_low_count(stock.borrow().low().count() as i32);
+    pantry_store.set_names(Rc::new(VecModel::from(
+        entries.iter().map(|entry| SharedString::from(entry.name.as_str())).collect::<Vec<_>>()
+    )).into());
+    let ui_ref = ui.as_weak();
+    let stock_ref = stock.clone();
+    let rows_ref = stock_rows.clone();
+    let blob_ref = pantry_blob.clone();
+    let buzzer_ref = buzzer.clone();
+    pantry_store.on_restock(move |name, amount| {
+        stock_ref.borrow_mut().restock(&name, amount);
+        if let Some(ui) = ui_ref.upgrade() {
+            if !save_pantry(&blob_ref, &stock_ref.borrow(), &rows_ref, &ui.global::<Pantry>()) {
+                buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+            }
+        }
+    });
+    let ui_ref = ui.as_weak();
+    let stock_ref = stock.clone();
+    let rows_ref = stock_rows.clone();
+    let blob_ref = pantry_blob.clone();
+    let buzzer_ref = buzzer.clone();
+    pantry_store.on_forget(move |name| {
+        stock_ref.borrow_mut().forget(&name);
+        if let Some(ui) = ui_ref.upgrade() {
+            if !save_pantry(&blob_ref, &stock_ref.borrow(), &rows_ref, &ui.global::<Pantry>()) {
+                buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+            }
+        }
+    });
+    let ui_ref = ui.as_weak();
+    let stock_ref = stock.clone();
+    let rows_ref = stock_rows.clone();
+    let blob_ref = pantry_blob.clone();
+    let buzzer_ref = buzzer.clone();
+    let entries_ref = entries.clone();
+    pantry_store.on_use_up(move |ingredients, recipe_progress| {
+        let used = pantry::used(&entries_ref, &ingredients, &recipe_progress);
+        // nothing to save if none of it's tracked
+        if !stock_ref.borrow_mut().use_up(used.iter().map(|(name, amount)| (name.as_str(), *amount))) {
+            return;
+        }
+        if let Some(ui) = ui_ref.upgrade() {
+            if !save_pantry(&blob_ref, &stock_ref.borrow(), &rows_ref, &ui.global::<Pantry>()) {
+                buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+            }
+        }
+    });
+
+    let loaded = recipes::load(&recipe_blob);
+    // cheap enough to do every time, and they get saved linked once
+    // they're next saved anyway
+    ingredients::link_recipes(&entries, &loaded);
+    let recipes = Rc::new(VecModel::from(loaded));
+    let expanded = Rc::new(VecModel::from(recipes::expand(&recipes.iter().collect::<Vec<_>>())));
+    let progresses = Rc::new(VecModel::from(
+        expanded.iter().map(|recipe| recipes::progress_for_recipe(&recipe)).collect::<Vec<_>>()
+    ));
+    let (journal, records) = Journal::open(flash.clone(), store::SESSIONS);
+    let journal = Rc::new(RefCell::new(journal));
+    sessions::restore(&records, &expanded, &progresses);
+
+    let history_log = Rc::new(RefCell::new(Log::open(flash.clone(), store::HISTORY)));
+    let clock = Rc::new(Cell::new(Clock::default()));
+    let session_log = ui.global::<SessionLog>();
+    let clock_ref = clock.clone();
+    session_log.on_now(move || clock_ref.get().now(now_ms()) as i32);
+    let log_ref = history_log.clone();
+    let clock_ref = clock.clone();
+    let buzzer_ref = buzzer.clone();
+    session_log.on_record(move |recipe, progress| {
+        let session = history::session(&recipe, &progress, &clock_ref.get(), now_ms());
+        if let Err(err) = history::save(&mut log_ref.borrow_mut(), &session) {
+            println!("couldn't log session: {}", err);
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        }
+    });
+    let ui_ref = ui.as_weak();
+    let log_ref = history_log.clone();
+    let clock_ref = clock.clone();
+    session_log.on_open(move || {
+        let Some(ui) = ui_ref.upgrade() else {
+            return;
+        };
+        let rows = history::rows(&history::load(&log_ref.borrow()), &clock_ref.get());
+        ui.global::<SessionLog>().set_sessions(Rc::new(VecModel::from(rows)).into());
+    });
+    let ui_ref = ui.as_weak();
+    session_log.on_close(move || {
+        // it's read back in next time, so don't hang on to it
+        if let Some(ui) = ui_ref.upgrade() {
+            ui.global::<SessionLog>().set_sessions(Default::default());
+        }
+    });
+    ui.set_recipes(recipes.clone().into());
+    ui.set_expanded_recipes(expanded.clone().into());
+    ui.set_recipe_progresses(progresses.clone().into());
+
+    let recent_blob = Blob::new(flash.clone(), store::RECENT);
+    let recent = Rc::new(RefCell::new(recipes::load_recent(&recent_blob)));
+    let folder = Rc::new(RefCell::new(String::new()));
+    let catalog_rows = Rc::new(RefCell::new(Vec::new()));
+    let recipe_index = ui.global::<RecipeIndex>();
+    show_catalog(&recipe_index, &recipes, &recent.borrow(), &folder.borrow(), &catalog_rows);
+    let ui_ref = ui.as_weak();
+    let recipes_ref = recipes.clone();
+    let recent_ref = recent.clone();
+    let folder_ref = folder.clone();
+    let rows_ref = catalog_rows.clone();
+    recipe_index.on_open(move |name| {
+        let Some(ui) = ui_ref.upgrade() else {
+            return -1;
+        };
+        let came_from = folder_ref.replace(name.as_str().into());
+        show_catalog(&ui.global::<RecipeIndex>(), &recipes_ref, &recent_ref.borrow(), &name, &rows_ref);
+        catalog::landing(&rows_ref.borrow(), &came_from)
+    });
+    let ui_ref = ui.as_weak();
+    let recipes_ref = recipes.clone();
+    let recent_ref = recent.clone();
+    let folder_ref = folder.clone();
+    let rows_ref = catalog_rows.clone();
+    let buzzer_ref = buzzer.clone();
+    recipe_index.on_used(move |idx| {
+        let Some(recipe) = usize::try_from(idx).ok().and_then(|idx| recipes_ref.row_data(idx)) else {
+            return;
+        };
+        scale_logic::catalog::opened(&mut recent_ref.borrow_mut(), &recipe.name);
+        if let Err(err) = recipes::save_recent(&recent_blob, &recent_ref.borrow()) {
+            println!("couldn't save recent recipes: {}", err);
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        }
+        if let Some(ui) = ui_ref.upgrade() {
+            show_catalog(&ui.global::<RecipeIndex>(), &recipes_ref, &recent_ref.borrow(), &folder_ref.borrow(), &rows_ref);
+        }
+    });
+    let rows_ref = catalog_rows.clone();
+    recipe_index.on_skip(move |from, direction| scale_logic::catalog::skip(&rows_ref.borrow(), from, direction));
+    let rows_ref = catalog_rows.clone();
+    recipe_index.on_jump(move |from, direction| scale_logic::catalog::jump(&rows_ref.borrow(), from, direction));
+    let rows_ref = catalog_rows.clone();
+    recipe_index.on_row_of(move |recipe, near| {
+        let Ok(recipe) = usize::try_from(recipe) else {
+            return -1;
+        };
+        scale_logic::catalog::row_of(&rows_ref.borrow(), recipe, near.max(0) as usize)
+            .map_or(-1, |row| row as i32)
+    });
+
+    let recipe_store = ui.global::<RecipeStore>();
+    let recipes_ref = recipes.clone();
+    recipe_store.on_begin_edit(move |idx| {
+        usize::try_from(idx).ok()
+            .and_then(|idx| recipes_ref.row_data(idx))
+            .map(|recipe| recipes::deep_copy(&recipe))
+            .unwrap_or_else(recipes::new_recipe)
+    });
+    let recipes_ref = recipes.clone();
+    let expanded_ref = expanded.clone();
+    let progresses_ref = progresses.clone();
+    let journal_ref = journal.clone();
+    let buzzer_ref = buzzer.clone();
+    let ui_ref = ui.as_weak();
+    let recent_ref = recent.clone();
+    let folder_ref = folder.clone();
+    let rows_ref = catalog_rows.clone();
+    recipe_store.on_save(move |idx, recipe| {
+        let stored = usize::try_from(idx).ok().and_then(|idx| recipes_ref.row_data(idx));
+        if stored.is_some_and(|stored| recipes::same_recipe(&stored, &recipe)) {
+            // nothing to write, and no progress to throw away
+            return idx;
+        }
+        // copy again so further edits to the draft don't leak in
+        let recipe = recipes::deep_copy(&recipe);
+        let idx = match usize::try_from(idx) {
+            Ok(idx) if idx < recipes_ref.row_count() => {
+                recipes_ref.set_row_data(idx, recipe);
+                idx
+            },
+            _ => {
+                recipes_ref.push(recipe);
+                recipes_ref.row_count() - 1
+            },
+        };
+        // anything that includes this recipe changes along with it
+        let all = recipes_ref.iter().collect::<Vec<_>>();
+        for (i, recipe) in recipes::expand(&all).into_iter().enumerate() {
+            match expanded_ref.row_data(i) {
+                Some(old) => {
+                    // the old progress might not even have the right
+                    // number of ingredients anymore
+                    let stale = i == idx || !recipes::same_ingredients(&old, &recipe);
+                    if stale {
+                        progresses_ref.set_row_data(i, recipes::progress_for_recipe(&recipe));
+                    }
+                    expanded_ref.set_row_data(i, recipe);
+                    if stale {
+                        // so the old progress doesn't come back at boot
+                        if let Err(err) = sessions::save(&mut journal_ref.borrow_mut(), &expanded_ref, &progresses_ref, i) {
+                            println!("couldn't save progress: {}", err);
+                        }
+                    }
+                },
+                None => {
+                    progresses_ref.push(recipes::progress_for_recipe(&recipe));
+                    expanded_ref.push(recipe);
+                },
+            }
+        }
+        if let Err(err) = recipes::save(&recipe_blob, &all) {
+            println!("couldn't save recipes: {}", err);
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        }
+        // its name or tags might have changed where it goes
+        if let Some(ui) = ui_ref.upgrade() {
+            show_catalog(&ui.global::<RecipeIndex>(), &recipes_ref, &recent_ref.borrow(), &folder_ref.borrow(), &rows_ref);
+        }
+        idx as i32
+    });
+    recipe_store.on_add_ingredient(|recipe| recipes::add_ingredient(&recipe));
+    recipe_store.on_remove_ingredient(|recipe, idx| {
+        if let Ok(idx) = usize::try_from(idx) {
+            recipes::remove_ingredient(&recipe, idx);
+        }
+    });
+    recipe_store.on_swap_ingredients(|recipe, a, b| {
+        if let (Ok(a), Ok(b)) = (usize::try_from(a), usize::try_from(b)) {
+            recipes::swap_ingredients(&recipe, a, b);
+        }
+    });
+
+    recipe_store.on_convert_basis(|recipe, basis| recipes::convert_basis(&recipe, basis));
+    let expanded_ref = expanded.clone();
+    let progresses_ref = progresses.clone();
+    let buzzer_ref = buzzer.clone();
+    recipe_store.on_progress_changed(move |idx| {
+        let Ok(idx) = usize::try_from(idx) else {
+            return;
+        };
+        if let Err(err) = sessions::save(&mut journal.borrow_mut(), &expanded_ref, &progresses_ref, idx) {
+            println!("couldn't save progress: {}", err);
+            buzzer_ref.borrow_mut().play(sounds::Sound::Error);
+        }
+    });
+    recipe_store.on_fresh_progress(|recipe| recipes::progress_for_recipe(&recipe));
+    let recipes_ref = recipes.clone();
+    recipe_store.on_actuals_version(move |recipe, progress| {
+        recipes::actuals_version(&recipes_ref.iter().collect::<Vec<_>>(), &recipe, &progress)
+    });
+
+    ui.global::<ProgressUtils>().on_completion(|progress| recipes::completion(&progress));
+
+    let summary_utils = ui.global::<SummaryUtils>();
+    summary_utils.on_batch_weight(|ingredients, progresses| recipes::batch_weight(&ingredients, &progresses));
+    summary_utils.on_planned_weight(|ingredients| recipes::planned_weight(&ingredients));
+
+    let baker_utils = ui.global::<BakerUtils>();
+    baker_utils.on_planned(|ingredients| recipes::planned(&ingredients));
+    baker_utils.on_done(|ingredients, progresses| recipes::done(&ingredients, &progresses));
+    baker_utils.on_flour_weight(|recipe| recipes::flour_weight(&recipe));
+
+    ui.global::<SectionUtils>().on_headers_through(|ingredients, idx| recipes::headers_through(&ingredients, idx));
+    ui.global::<StepUtils>().on_weigh_step(|steps, ingredient| recipes::weigh_step(&steps, ingredient));
+
+    let rebalance_utils = ui.global::<Rebalance>();
+    rebalance_utils.on_overage(|target, actual, tolerance| {
+        rebalance::overage(target, actual, tolerance).unwrap_or(0.)
+    });
+    rebalance_utils.on_rebalanced_factor(rebalance::rebalanced_factor);
+    rebalance_utils.on_top_up(rebalance::top_up);
+
+    let tolerance_utils = ui.global::<Tolerance>();
+    tolerance_utils.on_for_target(tolerance::for_target);
+    tolerance_utils.on_band(|target, actual, tolerance| to_tolerance_band(tolerance::band(target, actual, tolerance)));
+    tolerance_utils.on_pour(|pour, target, actual, tolerance| {
+        let band = match pour.band {
+            ToleranceBand::Under => Band::Under,
+            ToleranceBand::InRange => Band::InRange,
+            ToleranceBand::Over => Band::Over,
+        };
+        let mut next = tolerance::Pour { band, cued_in_range: pour.cued_in_range, cued_over: pour.cued_over };
+        let cue = next.step(target, actual, tolerance).unwrap_or(Band::Under);
+        Pour {
+            band: to_tolerance_band(next.band),
+            cued_in_range: next.cued_in_range,
+            cued_over: next.cued_over,
+            cue: to_tolerance_band(cue),
+        }
+    });
+
+    let portioning = ui.global::<Portioning>();
+    portioning.on_count_for_weight(|total, portion| portions::count_for_weight(total, portion) as i32);
+    portioning.on_target(|remaining, pieces_left| portions::target(remaining, pieces_left.max(0) as u32));
+    portioning.on_leftover_share(|remaining, pieces_left, pieces| {
+        portions::leftover_share(remaining, pieces_left.max(0) as u32, pieces.max(0) as u32)
+    });
+
+    ui.global::<ScaleFactorUtils>().on_position_of(|factors, factor| {
+        factors.iter()
+            .position(|f| (f - factor).abs() < 1e-4)
+            .map_or(-1, |idx| idx as i32)
+    });
+
+    ui.global::<TextUtils>().on_drop_last(|text| {
+        let mut text = String::from(text.as_str());
+        text.pop();
+        text.into()
+    });
+
+    let mut was_overloaded = false;
+    let mut watcher = Watcher::default();
+    let mut wheel = acceleration::Wheel::default();
     loop {
         loop {
             match event_consumer.dequeue() {
-                Some(Event::WheelCW) => {
+                Some(Event::WheelCW(at_us)) => {
+                    set_wheel_steps(&ui.global::<Wheel>(), wheel.turn(true, at_us));
                     window.dispatch_event(WindowEvent::KeyPressed { text: Key::UpArrow.into() });
                     window.dispatch_event(WindowEvent::KeyReleased { text: Key::UpArrow.into() });
                 },
-                Some(Event::WheelCCW) => {
+                Some(Event::WheelCCW(at_us)) => {
+                    set_wheel_steps(&ui.global::<Wheel>(), wheel.turn(false, at_us));
                     window.dispatch_event(WindowEvent::KeyPressed { text: Key::DownArrow.into() });
                     window.dispatch_event(WindowEvent::KeyReleased { text: Key::DownArrow.into() });
                 },
-                Some(Event::WheelButton(ButtonEvent::Press)) =>
-                    window.dispatch_event(WindowEvent::KeyPressed { text: Key::RightArrow.into() }),
+                Some(Event::WheelButton(ButtonEvent::Press)) => {
+                    buzzer.borrow_mut().play(sounds::Sound::Click);
+                    window.dispatch_event(WindowEvent::KeyPressed { text: Key::RightArrow.into() });
+                },
                 Some(Event::WheelButton(ButtonEvent::Release)) =>
                     window.dispatch_event(WindowEvent::KeyReleased { text: Key::RightArrow.into() }),
                 Some(Event::WheelButton(ButtonEvent::LongPress)) =>
                     window.dispatch_event(WindowEvent::KeyPressed { text: "d".into() }),
                 Some(Event::WheelButton(ButtonEvent::LongRelease)) =>
                     window.dispatch_event(WindowEvent::KeyReleased { text: "d".into() }),
-                Some(Event::BackButton(ButtonEvent::Press)) =>
-                    window.dispatch_event(WindowEvent::KeyPressed { text: Key::LeftArrow.into() }),
+                Some(Event::BackButton(ButtonEvent::Press)) => {
+                    buzzer.borrow_mut().play(sounds::Sound::Click);
+                    window.dispatch_event(WindowEvent::KeyPressed { text: Key::LeftArrow.into() });
+                },
                 Some(Event::BackButton(ButtonEvent::Release)) =>
                     window.dispatch_event(WindowEvent::KeyReleased { text: Key::LeftArrow.into() }),
                 Some(Event::BackButton(ButtonEvent::LongPress)) =>
@@ -634,20 +924,150 @@ fn main() -> ! {
             }
         }
 
-        let cur_weight = if let Some(weight) = scale.borrow_mut().step(&mut delay) {
-            ScaleStatus { valid: true, weight }
+        while let Ok(byte) = serial_rx.read_byte() {
+            match serial_line.push(byte) {
+                Some(Ok(Command::Stream { format, period_ms })) => {
+                    let new_stream = Stream::new(format, period_ms);
+                    if let Some(header) = new_stream.header() {
+                        println!("{}", header);
+                    }
+                    stream = Some(new_stream);
+                },
+                Some(Ok(Command::StreamOff)) =>
+                    stream = None,
+                Some(Ok(Command::Fake(val))) =>
+                    scale.borrow_mut().set_fake(Some(val)),
+                Some(Ok(Command::FakeOff)) =>
+                    scale.borrow_mut().set_fake(None),
+                Some(Ok(Command::Containers)) =>
+                    for container in containers.iter() {
+                        println!("{:.1} {}", container.tare * 1000., container.name);
+                    },
+                Some(Ok(Command::AddContainer { name, grams })) => {
+                    let container = Container { name: name.as_str().into(), tare: grams / 1000. };
+                    if !add_container(&container_blob, &containers, container) {
+                        buzzer.borrow_mut().play(sounds::Sound::Error);
+                    }
+                },
+                Some(Ok(Command::RemoveContainer { name })) =>
+                    match containers.iter().position(|container| container.name.as_str() == name.as_str()) {
+                        Some(idx) => {
+                            containers.remove(idx);
+                            if !save_containers(&container_blob, &containers) {
+                                buzzer.borrow_mut().play(sounds::Sound::Error);
+                            }
+                        },
+                        None => {
+                            println!("error: no container called {}", name);
+                            buzzer.borrow_mut().play(sounds::Sound::Error);
+                        },
+                    },
+                Some(Ok(Command::Pantry { low_only })) =>
+                    for item in stock.borrow().stock.iter().filter(|item| !low_only || item.is_low()) {
+                        println!("{:.0} {}{}", item.amount * 1000., item.name, if item.is_low() { " low" } else { "" });
+                    },
+                Some(Ok(Command::History(format))) => {
+                    // a session at a time, since the whole log might not fit
+                    let log = history_log.borrow();
+                    match format {
+                        StreamFormat::Csv => {
+                            println!("{}", scale_logic::history::CSV_HEADER);
+                            for (idx, session) in history::sessions(&log).enumerate() {
+                                for row in session.csv_rows(idx) {
+                                    println!("{}", row);
+                                }
+                            }
+                        },
+                        StreamFormat::Json =>
+                            for (idx, session) in history::sessions(&log).enumerate() {
+                                println!("{}", session.json(idx));
+                            },
+                    }
+                },
+                Some(Ok(Command::Clock { unix, utc_offset })) => {
+                    let mut updated = clock.get();
+                    updated.set(unix, now_ms());
+                    updated.utc_offset = utc_offset;
+                    clock.set(updated);
+                },
+                Some(Err(err)) => {
+                    println!("error: {}", err);
+                    buzzer.borrow_mut().play(sounds::Sound::Error);
+                },
+                None =>
+                    (),
+            }
+        }
+
+        let reading = scale.borrow_mut().step(&mut delay);
+        let cur_weight = if let Some(reading) = reading {
+            ScaleStatus { valid: true, weight: reading.weight }
         } else {
             ScaleStatus { valid: false, weight: 0. }
         };
         ui.set_current_weight(cur_weight);
 
-        slint::platform::update_timers_and_animations();
+        let overloaded = reading.as_ref().is_some_and(Reading::overloaded);
+        if overloaded && !was_overloaded {
+            buzzer.borrow_mut().play(sounds::Sound::Overload);
+        }
+        was_overloaded = overloaded;
+
+        if let Some(reading) = reading.as_ref() {
+            let change = watcher.step(reading.gross, now_ms());
+            if ui.global::<SettingsStore>().get_settings().recognize_containers {
+                let container_store = ui.global::<ContainerStore>();
+                match change {
+                    // only onto an empty scale that isn't tared for
+                    // anything else
+                    Some(Change::Placed(weight)) if (reading.gross - reading.filtered).abs() < spotting::EMPTY => {
+                        let found = spotting::recognize(containers.iter().map(|container| container.tare), weight);
+                        if let Some(container) = found.and_then(|idx| containers.row_data(idx)) {
+                            tare_for(&mut scale.borrow_mut(), &container_store, Some(container));
+                            spotted.set(true);
+                            buzzer.borrow_mut().play(sounds::Sound::InRange);
+                        }
+                    },
+                    Some(Change::Emptied) if spotted.get() => {
+                        tare_for(&mut scale.borrow_mut(), &container_store, None);
+                        spotted.set(false);
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        if let (Some(counter), Some(reading)) = (counter.borrow_mut().as_mut(), reading.as_ref()) {
+            if let Some(unit) = counter.step(reading.filtered, now_ms()) {
+                let piece_counter = ui.global::<PieceCounter>();
+                piece_counter.set_unit(unit);
+                piece_counter.set_too_light(counting::too_light(unit));
+            }
+        }
+
+        if let (Some(trend), Some(reading)) = (trend.borrow_mut().as_mut(), reading.as_ref()) {
+            if let Some(rate) = trend.step(reading.filtered, now_ms()) {
+                let weight_trend = ui.global::<WeightTrend>();
+                weight_trend.set_rate(rate);
+                weight_trend.set_rate_known(true);
+            }
+        }
 
-        i += 1;
-        if i > 230 {
-            i = 0;
+        if let (Some(stream), Some(reading)) = (stream.as_mut(), reading.as_ref()) {
+            let sample = Sample { raw: reading.raw, weight: reading.weight, filtered: reading.filtered };
+            if let Some(line) = stream.poll(now_ms(), &sample) {
+                println!("{}", line);
+            }
         }
 
+        if timers.borrow_mut().poll(now_ms()) > 0 {
+            buzzer.borrow_mut().play(sounds::Sound::TimerDone);
+        }
+        sync_timers(&timers.borrow(), &timer_rows, now_ms());
+
+        slint::platform::update_timers_and_animations();
+        buzzer.borrow_mut().poll(now_ms());
+
         // Draw the scene if something needs to be drawn.
         window.draw_if_needed(|renderer| {
             renderer.set_rendering_rotation(RenderingRotation::Rotate90);
@@ -661,12 +1081,15 @@ fn main() -> ! {
             let after_wait = now_us();
             let _res = unsafe { display.fill_with_framebuffer(cast_pixel_buffer(&framebuf[..])) };
             let after_fill = now_us();
-            println!(
-                "render: {}us, wait: {}us, fill: {}us",
-                after_render.wrapping_sub(before_render),
-                after_wait.wrapping_sub(after_render),
-                after_fill.wrapping_sub(after_wait),
-            );
+            // it'd only get mixed in with the stream
+            if stream.is_none() {
+                println!(
+                    "render: {}us, wait: {}us, fill: {}us",
+                    after_render.wrapping_sub(before_render),
+                    after_wait.wrapping_sub(after_render),
+                    after_fill.wrapping_sub(after_wait),
+                );
+            }
         });
 
         if !window.has_active_animations() {
@@ -675,6 +1098,126 @@ fn main() -> ! {
     }
 }
 
+/// Tares for a container, or for nothing at all.
+fn tare_for<I: I2cTrait>(scale: &mut Scale<I>, store: &ContainerStore, container: Option<Container>) {
+    match container {
+        Some(container) => {
+            scale.tare_to(container.tare);
+            store.set_current(container.name);
+        },
+        None => {
+            scale.tare_to(0.);
+            store.set_current(SharedString::new());
+        },
+    }
+}
+
+// checks a new container's fine to keep, then keeps it
+fn add_container<F: Storage>(blob: &Blob<F>, containers: &VecModel<Container>, container: Container) -> bool {
+    if let Err(err) = containers::check(&containers.iter().collect::<Vec<_>>(), &container) {
+        println!("couldn't add container {}: {}", container.name, err);
+        return false;
+    }
+    containers.push(container);
+    save_containers(blob, containers)
+}
+
+fn save_containers<F: Storage>(blob: &Blob<F>, containers: &VecModel<Container>) -> bool {
+    match containers::save(blob, &containers.iter().collect::<Vec<_>>()) {
+        Ok(()) => true,
+        Err(err) => {
+            println!("couldn't save containers: {}", err);
+            false
+        },
+    }
+}
+
+// shows what's in stock and saves it
+fn save_pantry<F: Storage>(
+    blob: &Blob<F>,
+    stock: &scale_logic::pantry::Pantry,
+    rows: &VecModel<StockItem>,
+    store: &Pantry,
+) -> bool {
+    rows.set_vec(pantry::rows(stock));
+    store.set_low_count(stock.low().count() as i32);
+    match pantry::save(blob, stock) {
+        Ok(()) => true,
+        Err(err) => {
+            println!("couldn't save the pantry: {}", err);
+            false
+        },
+    }
+}
+
+// lays out the recipe list for a folder, or the top with ""
+fn show_catalog(
+    index: &RecipeIndex,
+    recipes: &VecModel<Recipe>,
+    recent: &[String],
+    folder: &str,
+    rows: &RefCell<Vec<scale_logic::catalog::Row>>,
+) {
+    let new_rows = catalog::rows(recipes, recent, folder);
+    index.set_entries(Rc::new(VecModel::from(catalog::entries(&new_rows, recipes))).into());
+    index.set_folder(folder.into());
+    *rows.borrow_mut() = new_rows;
+}
+
+fn timer_row(timer: &Timer<SharedString>, now_ms: u64) -> KitchenTimer {
+    KitchenTimer { id: timer.id, name: timer.name.clone(), seconds_left: timer.seconds_left(now_ms) as i32 }
+}
+
+/// Brings the UI's copy of the timers up to date, only touching rows
+/// that actually changed so they don't redraw every time around.
+fn sync_timers(timers: &Timers<SharedString>, rows: &VecModel<KitchenTimer>, now_ms: u64) {
+    if rows.row_count() != timers.len() {
+        rows.set_vec(timers.iter().map(|timer| timer_row(timer, now_ms)).collect::<Vec<_>>());
+        return;
+    }
+    for (idx, timer) in timers.iter().enumerate() {
+        let row = timer_row(timer, now_ms);
+        if rows.row_data(idx).as_ref() != Some(&row) {
+            rows.set_row_data(idx, row);
+        }
+    }
+}
+
+// how far the next arrow key should go, given how fast the wheel's going
+fn set_wheel_steps(global: &Wheel, interval_us: u64) {
+    global.set_list_steps(acceleration::steps(acceleration::LIST, interval_us));
+    global.set_value_steps(acceleration::steps(acceleration::VALUE, interval_us));
+}
+
+fn sound_for(sound: Sound) -> sounds::Sound {
+    match sound {
+        Sound::Click => sounds::Sound::Click,
+        Sound::InRange => sounds::Sound::InRange,
+        Sound::Over => sounds::Sound::Over,
+        Sound::Overload => sounds::Sound::Overload,
+        Sound::TimerDone => sounds::Sound::TimerDone,
+        Sound::LowBattery => sounds::Sound::LowBattery,
+        Sound::Error => sounds::Sound::Error,
+    }
+}
+
+fn to_tolerance_band(band: Band) -> ToleranceBand {
+    match band {
+        Band::Under => ToleranceBand::Under,
+        Band::InRange => ToleranceBand::InRange,
+        Band::Over => ToleranceBand::Over,
+    }
+}
+
+fn apply_settings(buzzer: &mut Sequencer<LedcBuzzer>, settings: &Settings) {
+    buzzer.set_volume(settings.volume.clamp(0, sounds::MAX_VOLUME as i32) as u8);
+    buzzer.set_muted(settings.muted);
+}
+
 fn now_us() -> u64 {
     time::Instant::now().duration_since_epoch().as_micros()
 }
+
+fn now_ms() -> u64 {
+    now_us() / 1000
+}
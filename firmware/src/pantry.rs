@@ -0,0 +1,80 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! What's left in the pantry, saved between boots.
+
+use alloc::vec::Vec;
+
+use embedded_storage::Storage;
+use scale_logic::ingredients::Entry;
+use scale_logic::pantry::{Pantry, Stock};
+use slint::{Model, ModelRc, SharedString};
+
+use crate::ingredients;
+use crate::store::{Blob, Decoder, Encoder};
+use crate::{Ingredient, IngredientKind, RecipeProgress, StockItem};
+
+const VERSION: u32 = 1;
+
+fn encode(pantry: &Pantry) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.u32(pantry.stock.len() as u32);
+    for stock in &pantry.stock {
+        encoder.str(&stock.name);
+        encoder.f32(stock.amount);
+        encoder.f32(stock.low);
+    }
+    encoder.finish()
+}
+
+fn decode(data: &[u8]) -> Option<Pantry> {
+    let mut decoder = Decoder::new(data);
+    if decoder.u32()? != VERSION {
+        return None;
+    }
+    let count = decoder.u32()?;
+    let stock = (0..count)
+        .map(|_| Some(Stock { name: decoder.str()?.into(), amount: decoder.f32()?, low: decoder.f32()? }))
+        .collect::<Option<_>>()?;
+    Some(Pantry { stock })
+}
+
+/// Loads what's in stock, or an empty pantry if nothing's been weighed
+/// in yet.
+pub fn load<F: Storage>(blob: &Blob<F>) -> Pantry {
+    blob.load().and_then(|data| decode(&data)).unwrap_or_default()
+}
+
+pub fn save<F: Storage>(blob: &Blob<F>, pantry: &Pantry) -> Result<(), &'static str> {
+    blob.save(&encode(pantry))
+}
+
+/// What a session used of each ingredient in the database, in kg: what
+/// was weighed out, or what was measured for the rest.
+pub fn used(
+    entries: &[Entry],
+    ingredients: &ModelRc<Ingredient>,
+    recipe_progress: &RecipeProgress,
+) -> Vec<(SharedString, f32)> {
+    ingredients.iter()
+        .zip(recipe_progress.ingredient_progresses.iter())
+        .filter(|(ingredient, progress)| progress.done && !ingredient.entry.is_empty())
+        .map(|(ingredient, progress)| {
+            let amount = match ingredient.kind {
+                IngredientKind::Weighed => progress.amount,
+                _ => {
+                    let scaled = ingredient.amount * recipe_progress.scale_factor;
+                    ingredients::weight(entries, &Ingredient { amount: scaled, ..ingredient.clone() })
+                },
+            };
+            (ingredient.entry, amount)
+        })
+        .collect()
+}
+
+pub fn rows(pantry: &Pantry) -> Vec<StockItem> {
+    pantry.stock.iter()
+        .map(|stock| StockItem { name: stock.name.as_str().into(), amount: stock.amount, low: stock.is_low() })
+        .collect()
+}
@@ -0,0 +1,872 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! The recipes themselves: the ones we ship with, and saving and
+//! loading edited ones.
+
+use core::{iter, slice};
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use embedded_storage::Storage;
+use slint::{Model, ModelRc, SharedString, VecModel};
+
+use crate::store::{Blob, Decoder, Encoder};
+use crate::{
+    Basis, Component, FlourLiquid, Ingredient, IngredientKind, IngredientProgress, IngredientRole, Recipe,
+    RecipeProgress, Step, StepKind,
+};
+
+fn ingredient(name: &str, amount: f32) -> Ingredient {
+    measured(name, amount, IngredientKind::Weighed)
+}
+
+fn measured(name: &str, amount: f32, kind: IngredientKind) -> Ingredient {
+    Ingredient {
+        name: name.into(),
+        amount,
+        kind,
+        section: "".into(),
+        role: IngredientRole::Other,
+        tolerance: 0.,
+        entry: "".into(),
+    }
+}
+
+fn flour(name: &str, amount: f32) -> Ingredient {
+    Ingredient { role: IngredientRole::Flour, ..ingredient(name, amount) }
+}
+
+fn liquid(name: &str, amount: f32) -> Ingredient {
+    Ingredient { role: IngredientRole::Liquid, ..ingredient(name, amount) }
+}
+
+fn section<const N: usize>(name: &str, ingredients: [Ingredient; N]) -> [Ingredient; N] {
+    ingredients.map(|ingredient| Ingredient { section: name.into(), ..ingredient })
+}
+
+fn component(recipe: &str, factor: f32) -> Component {
+    Component { recipe: recipe.into(), factor }
+}
+
+fn weigh(ingredient: usize) -> Step {
+    Step { kind: StepKind::Weigh, ingredient: ingredient as i32, text: "".into(), seconds: 0 }
+}
+
+fn instruction(text: &str) -> Step {
+    Step { kind: StepKind::Instruction, ingredient: 0, text: text.into(), seconds: 0 }
+}
+
+fn timer(text: &str, seconds: i32) -> Step {
+    Step { kind: StepKind::Timer, ingredient: 0, text: text.into(), seconds }
+}
+
+/// A recipe that's nothing more than weighing everything in order.
+fn recipe(name: &str, ingredients: Vec<Ingredient>) -> Recipe {
+    let steps = (0..ingredients.len()).map(weigh).collect();
+    recipe_with_steps(name, ingredients, steps)
+}
+
+fn recipe_with_steps(name: &str, ingredients: Vec<Ingredient>, steps: Vec<Step>) -> Recipe {
+    Recipe {
+        name: name.into(),
+        ingredients: ModelRc::new(VecModel::from(ingredients)),
+        steps: ModelRc::new(VecModel::from(steps)),
+        components: ModelRc::new(VecModel::<Component>::default()),
+        basis: Basis::Weights,
+        basis_weight: 0.,
+        tags: SharedString::new(),
+        favorite: false,
+    }
+}
+
+/// Marks a recipe as written in baker's percentages (as fractions) of
+/// flour or dough, making `weight` kg of it. The amounts have to be
+/// given as fractions already; this doesn't convert them.
+fn bakers(basis: Basis, weight: f32, recipe: Recipe) -> Recipe {
+    Recipe { basis, basis_weight: weight, ..recipe }
+}
+
+/// Files a recipe under some comma-separated tags.
+fn tagged(tags: &str, recipe: Recipe) -> Recipe {
+    Recipe { tags: tags.into(), ..recipe }
+}
+
+/// A recipe made entirely out of other ones.
+fn recipe_of_components(name: &str, components: Vec<Component>) -> Recipe {
+    Recipe { components: ModelRc::new(VecModel::from(components)), ..recipe(name, Vec::new()) }
+}
+
+fn vegan_choux() -> Recipe {
+    recipe(
+        "Vegan Choux",
+        [
+            ingredient("water", 0.06),
+            ingredient("soy milk", 0.06),
+            ingredient("vanilla extract", 0.005),
+            ingredient("sugar", 0.006),
+            ingredient("vegan butter", 0.028),
+            ingredient("all-purpose flour", 0.065),
+            ingredient("Just Egg", 0.125),
+            ingredient("soy milk", 0.030),
+        ].into(),
+    )
+}
+
+fn vegan_creme_pat() -> Recipe {
+    recipe(
+        "Vegan Creme Pat",
+        [
+            // ingredient("soy milk", 0.243),
+            // ingredient("vanilla extract", 0.010),
+            // ingredient("salt", 0.001),
+            // ingredient("corn starch", 0.016),
+            // ingredient("sugar", 0.050),
+            // ingredient("Just Egg", 0.083),
+            // ingredient("vegan butter", 0.042),
+            ingredient("soy milk", 0.486),
+            ingredient("vanilla extract", 0.020),
+            ingredient("salt", 0.002),
+            ingredient("corn starch", 0.032),
+            ingredient("sugar", 0.100),
+            ingredient("Just Egg", 0.166),
+            ingredient("vegan butter", 0.084)
+        ].into(),
+    )
+}
+
+fn choux() -> Recipe {
+    recipe(
+        "Choux",
+        [
+            ingredient("water", 0.235),
+            ingredient("butter", 0.084),
+            ingredient("sugar", 0.008),
+            ingredient("salt", 0.002),
+            ingredient("all-purpose flour", 0.128),
+            ingredient("eggs", 0.200),
+        ].into(),
+    )
+}
+
+fn creme_pat() -> Recipe {
+    recipe(
+        "Creme Pat",
+        [
+            ingredient("milk", 0.455),
+            ingredient("vanilla bean", 0.001),
+            ingredient("sugar", 0.115),
+            ingredient("corn starch", 0.030),
+            ingredient("salt", 0.001),
+            ingredient("egg yolks", 0.070),
+            ingredient("butter", 0.030),
+        ].into(),
+    )
+}
+
+fn pasta_dough() -> Recipe {
+    recipe(
+        "Egg Pasta",
+        [
+            ingredient("flour", 0.255),
+            ingredient("whole eggs", 0.110),
+            ingredient("egg yolks", 0.070),
+            ingredient("salt", 0.003),
+        ].into(),
+    )
+}
+
+fn poolish_bread() -> Recipe {
+    bakers(Basis::Flour, 1., recipe_with_steps(
+        "Poolish Bread",
+        [
+            section("poolish", [
+                flour("flour", 0.5),
+                ingredient("yeast", 0.0004),
+                liquid("water (80F)", 0.5),
+            ]).as_slice(),
+            &section("dough", [
+                flour("flour", 0.5),
+                ingredient("salt", 0.021),
+                ingredient("yeast", 0.003),
+                liquid("water (105F)", 0.25),
+            ]),
+        ].concat(),
+        [
+            weigh(0),
+            weigh(1),
+            weigh(2),
+            instruction("mix the poolish and cover"),
+            timer("rest overnight", 12 * 60 * 60),
+            weigh(3),
+            weigh(4),
+            weigh(5),
+            weigh(6),
+            instruction("mix in the poolish and knead"),
+        ].into(),
+    ))
+}
+
+fn focaccia() -> Recipe {
+    bakers(Basis::Flour, 0.5, recipe(
+        "Focaccia",
+        [
+            section("dough", [
+                flour("flour", 1.),
+                ingredient("salt", 0.02),
+                ingredient("yeast", 0.008),
+                liquid("water (roomtemp)", 0.8),
+                ingredient("olive oil", 0.04),
+            ]).as_slice(),
+            &section("pan", [ingredient("olive oil", 0.056)]),
+            &section("topping", [ingredient("olive oil", 0.04)]),
+        ].concat(),
+    ))
+}
+
+fn kouign_amann() -> Recipe {
+    recipe(
+        "Kouign Amann",
+        [
+            ingredient("flour", 0.213),
+            ingredient("salt", 0.0032),
+            ingredient("yeast", 0.0016),
+            ingredient("water (75F)", 0.145),
+            ingredient("salted butter", 0.134),
+            ingredient("sugar", 0.156),
+        ].into(),
+    )
+}
+
+fn pie_dough() -> Recipe {
+    recipe(
+        "Pie Dough",
+        [
+            ingredient("low-protein APF", 0.225),
+            ingredient("sugar", 0.015),
+            ingredient("salt", 0.004),
+            ingredient("unsalted butter", 0.225),
+            ingredient("cold tap water", 0.115),
+        ].into(),
+    )
+}
+
+fn butternut_pie() -> Recipe {
+    recipe(
+        "Butternut Pie",
+        [
+            ingredient("butternut puree", 0.395),
+            ingredient("condensed milk", 0.680),
+            ingredient("light brown sugar", 0.115),
+            ingredient("vanilla extract", 0.015),
+            measured("ground ginger", 1.5, IngredientKind::Teaspoons),
+            measured("ground cinnamon", 1.5, IngredientKind::Teaspoons),
+            measured("grated nutmeg", 0.25, IngredientKind::Teaspoons),
+            ingredient("salt", 0.001),
+            measured("ground cloves", 0.125, IngredientKind::Teaspoons),
+            ingredient("unsalted butter", 0.030),
+            measured("eggs", 3., IngredientKind::Counted),
+        ].into(),
+    )
+}
+
+fn cream_puffs() -> Recipe {
+    recipe_of_components(
+        "Cream Puffs",
+        [
+            // first, so it has time to chill while the shells bake
+            component("Creme Pat", 1.),
+            component("Choux", 1.),
+        ].into(),
+    )
+}
+
+pub fn progress_for_recipe(recipe: &Recipe) -> RecipeProgress {
+    RecipeProgress {
+        scale_factor: 1.0,
+        current_ingredient: 0,
+        cumulative: false,
+        bowl_weight: 0.0,
+        started: 0,
+        finished: false,
+        ingredient_progresses:
+            iter::repeat(IngredientProgress { done: false, amount: 0.0 })
+            .take(recipe.ingredients.row_count())
+            .collect::<Vec<_>>()[..]
+            .into(),
+    }
+}
+
+/// The share of a recipe's ingredients that are in, from 0 to 1.
+pub fn completion(progress: &RecipeProgress) -> f32 {
+    let total = progress.ingredient_progresses.row_count();
+    if total == 0 {
+        return 0.;
+    }
+    let done = progress.ingredient_progresses.iter().filter(|progress| progress.done).count();
+    done as f32 / total as f32
+}
+
+pub fn defaults() -> Vec<Recipe> {
+    [
+        tagged("pastry, vegan", vegan_choux()),
+        tagged("pastry, vegan", vegan_creme_pat()),
+        tagged("pastry", choux()),
+        tagged("pastry", creme_pat()),
+        tagged("pasta", pasta_dough()),
+        tagged("bread", poolish_bread()),
+        tagged("bread", focaccia()),
+        tagged("bread, pastry", kouign_amann()),
+        tagged("pie", pie_dough()),
+        tagged("pie", butternut_pie()),
+        tagged("pastry", cream_puffs()),
+    ].into()
+}
+
+/// Copies a recipe, including its ingredients, into a fresh model
+/// that can be edited without touching the original.
+pub fn deep_copy(recipe: &Recipe) -> Recipe {
+    Recipe {
+        name: recipe.name.clone(),
+        ingredients: ModelRc::new(VecModel::from(recipe.ingredients.iter().collect::<Vec<_>>())),
+        steps: ModelRc::new(VecModel::from(recipe.steps.iter().collect::<Vec<_>>())),
+        components: ModelRc::new(VecModel::from(recipe.components.iter().collect::<Vec<_>>())),
+        basis: recipe.basis,
+        basis_weight: recipe.basis_weight,
+        tags: recipe.tags.clone(),
+        favorite: recipe.favorite,
+    }
+}
+
+pub fn new_recipe() -> Recipe {
+    recipe("", Vec::new())
+}
+
+// "Bread v3" is version 3 of "Bread", and "Bread" itself is version 1
+fn split_version(name: &str) -> (&str, u32) {
+    name.rsplit_once(" v")
+        .and_then(|(base, version)| Some((base, version.parse().ok()?)))
+        .unwrap_or((name, 1))
+}
+
+/// The name for the next version of a recipe: one past the latest
+/// version of it there already is.
+fn next_version_name(name: &str, names: impl Iterator<Item = SharedString>) -> SharedString {
+    let (base, _) = split_version(name);
+    let latest = names
+        .filter_map(|other| match split_version(&other) {
+            (other_base, version) if other_base == base => Some(version),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(1);
+    format!("{} v{}", base, latest + 1).into()
+}
+
+/// A new version of a recipe with what actually went in as its
+/// amounts, and the planned ones for anything that didn't. The progress
+/// is kept against the expanded recipe, so the actuals are mapped back
+/// onto the stored one's own ingredients, at its own size and on its
+/// own basis; its components are kept as they were planned. If it's
+/// gone, or changed since, the version comes out flat and by weight,
+/// at this batch's size instead.
+pub fn actuals_version(recipes: &[Recipe], expanded: &Recipe, progress: &RecipeProgress) -> Recipe {
+    let actuals: Vec<Ingredient> = expanded.ingredients.iter()
+        .zip(progress.ingredient_progresses.iter())
+        .map(|(ingredient, ingredient_progress)| Ingredient {
+            amount: if ingredient_progress.done {
+                ingredient_progress.amount
+            } else {
+                ingredient.amount * progress.scale_factor
+            },
+            ..ingredient
+        })
+        .collect();
+    let name = next_version_name(&expanded.name, recipes.iter().map(|recipe| recipe.name.clone()));
+    let stored = recipes.iter()
+        .find(|recipe| recipe.name == expanded.name)
+        .filter(|stored| {
+            progress.scale_factor > 0. && stored.ingredients.row_count() <= actuals.len()
+                && stored.ingredients.iter().zip(&actuals)
+                    .all(|(ingredient, actual)| ingredient.name == actual.name && ingredient.kind == actual.kind)
+        });
+    let Some(stored) = stored else {
+        return recipe_with_steps(&name, actuals, expanded.steps.iter().collect());
+    };
+    let mut version = Recipe {
+        name,
+        ingredients: ModelRc::new(VecModel::from(stored.ingredients.iter()
+            .zip(actuals)
+            .map(|(ingredient, actual)| Ingredient {
+                amount: actual.amount / progress.scale_factor,
+                ..ingredient
+            })
+            .collect::<Vec<_>>())),
+        basis: Basis::Weights,
+        basis_weight: 0.,
+        favorite: false,
+        ..deep_copy(stored)
+    };
+    if stored.basis != Basis::Weights {
+        version.basis_weight = convert_basis(&version, stored.basis);
+        version.basis = stored.basis;
+    }
+    version
+}
+
+/// The ingredients of a recipe made by `deep_copy` or `new_recipe`,
+/// which can have ingredients added and removed.
+pub fn editable_ingredients(recipe: &Recipe) -> Option<&VecModel<Ingredient>> {
+    recipe.ingredients.as_any().downcast_ref::<VecModel<Ingredient>>()
+}
+
+/// Likewise for the steps.
+pub fn editable_steps(recipe: &Recipe) -> Option<&VecModel<Step>> {
+    recipe.steps.as_any().downcast_ref::<VecModel<Step>>()
+}
+
+/// Adds an empty ingredient to the end of an editable recipe, along
+/// with a step to weigh it.
+pub fn add_ingredient(recipe: &Recipe) {
+    if let (Some(ingredients), Some(steps)) = (editable_ingredients(recipe), editable_steps(recipe)) {
+        steps.push(weigh(ingredients.row_count()));
+        ingredients.push(ingredient("", 0.));
+    }
+}
+
+/// Removes an ingredient from an editable recipe, along with the step
+/// that weighs it.
+pub fn remove_ingredient(recipe: &Recipe, idx: usize) {
+    let (Some(ingredients), Some(steps)) = (editable_ingredients(recipe), editable_steps(recipe)) else {
+        return;
+    };
+    if idx >= ingredients.row_count() {
+        return;
+    }
+    ingredients.remove(idx);
+    let kept: Vec<_> = steps.iter()
+        .filter(|step| step.kind != StepKind::Weigh || step.ingredient != idx as i32)
+        .map(|step| match step.kind {
+            StepKind::Weigh if step.ingredient > idx as i32 => Step { ingredient: step.ingredient - 1, ..step },
+            _ => step,
+        })
+        .collect();
+    steps.set_vec(kept);
+}
+
+/// Swaps two ingredients in an editable recipe. The steps stay put, so
+/// whichever ingredient ends up first gets weighed first.
+pub fn swap_ingredients(recipe: &Recipe, a: usize, b: usize) {
+    if let Some(ingredients) = editable_ingredients(recipe) {
+        let len = ingredients.row_count();
+        if a != b && a < len && b < len {
+            let (a, b) = (a.min(b), a.max(b));
+            let later = ingredients.remove(b);
+            let earlier = ingredients.remove(a);
+            ingredients.insert(a, later);
+            ingredients.insert(b, earlier);
+        }
+    }
+}
+
+/// The step that weighs an ingredient, or the first one if none does.
+pub fn weigh_step(steps: &ModelRc<Step>, ingredient: i32) -> i32 {
+    steps.iter()
+        .position(|step| step.kind == StepKind::Weigh && step.ingredient == ingredient)
+        .map_or(0, |idx| idx as i32)
+}
+
+/// How many section headers the ingredient list shows down to and
+/// including the ingredient at `idx`.
+pub fn headers_through(ingredients: &ModelRc<Ingredient>, idx: i32) -> i32 {
+    let mut prev = SharedString::new();
+    let mut count = 0;
+    for ingredient in ingredients.iter().take((idx + 1).max(0) as usize) {
+        if !ingredient.section.is_empty() && ingredient.section != prev {
+            count += 1;
+        }
+        prev = ingredient.section;
+    }
+    count
+}
+
+// Deep enough for anything sensible, shallow enough that a recipe
+// which (eventually) includes itself doesn't take us down with it.
+const MAX_DEPTH: usize = 4;
+
+fn weighed_total(ingredients: impl Iterator<Item = Ingredient>) -> f32 {
+    ingredients
+        .filter(|ingredient| ingredient.kind == IngredientKind::Weighed)
+        .map(|ingredient| ingredient.amount)
+        .sum()
+}
+
+/// What an amount of 1 (100%) of a weighed ingredient comes out to, in
+/// kg.
+fn mass_factor(recipe: &Recipe) -> f32 {
+    match recipe.basis {
+        Basis::Weights => 1.,
+        Basis::Flour => recipe.basis_weight,
+        Basis::Dough => {
+            let total = weighed_total(recipe.ingredients.iter());
+            if total > 0. { recipe.basis_weight / total } else { 0. }
+        },
+    }
+}
+
+/// How much flour a recipe calls for, in kg.
+pub fn flour_weight(recipe: &Recipe) -> f32 {
+    match recipe.basis {
+        Basis::Weights => totals(recipe.ingredients.iter()).flour,
+        // what 100% means, whether or not the flour is marked as such
+        _ => mass_factor(recipe),
+    }
+}
+
+fn totals(ingredients: impl Iterator<Item = Ingredient>) -> FlourLiquid {
+    let mut totals = FlourLiquid { flour: 0., liquid: 0. };
+    for ingredient in ingredients.filter(|ingredient| ingredient.kind == IngredientKind::Weighed) {
+        match ingredient.role {
+            IngredientRole::Flour => totals.flour += ingredient.amount,
+            IngredientRole::Liquid => totals.liquid += ingredient.amount,
+            IngredientRole::Other => (),
+        }
+    }
+    totals
+}
+
+/// All the flour and liquid a recipe's ingredients call for.
+pub fn planned(ingredients: &ModelRc<Ingredient>) -> FlourLiquid {
+    totals(ingredients.iter())
+}
+
+// the ingredients that are in, with what actually went in as their
+// amounts
+fn weighed_in<'a>(
+    ingredients: &'a ModelRc<Ingredient>,
+    progresses: &'a ModelRc<IngredientProgress>,
+) -> impl Iterator<Item = Ingredient> + 'a {
+    ingredients.iter().zip(progresses.iter())
+        .filter(|(_, progress)| progress.done)
+        .map(|(ingredient, progress)| Ingredient { amount: progress.amount, ..ingredient })
+}
+
+/// Everything there is to weigh in, in kg, before scaling.
+pub fn planned_weight(ingredients: &ModelRc<Ingredient>) -> f32 {
+    weighed_total(ingredients.iter())
+}
+
+/// Everything weighed in so far, in kg.
+pub fn batch_weight(ingredients: &ModelRc<Ingredient>, progresses: &ModelRc<IngredientProgress>) -> f32 {
+    weighed_total(weighed_in(ingredients, progresses))
+}
+
+/// The flour and liquid weighed in so far.
+pub fn done(ingredients: &ModelRc<Ingredient>, progresses: &ModelRc<IngredientProgress>) -> FlourLiquid {
+    totals(weighed_in(ingredients, progresses))
+}
+
+/// Rewrites an editable recipe's weighed amounts for a different
+/// basis without changing what they weigh, returning the new basis
+/// weight. With no flour marked, percentages are of 1kg instead.
+pub fn convert_basis(recipe: &Recipe, basis: Basis) -> f32 {
+    let Some(ingredients) = editable_ingredients(recipe) else {
+        return recipe.basis_weight;
+    };
+    let factor = mass_factor(recipe);
+    let weights: Vec<Ingredient> = ingredients.iter()
+        .map(|ingredient| match ingredient.kind {
+            IngredientKind::Weighed => Ingredient { amount: ingredient.amount * factor, ..ingredient },
+            _ => ingredient,
+        })
+        .collect();
+    let flour = match totals(weights.iter().cloned()).flour {
+        flour if flour > 0. => flour,
+        _ => 1.,
+    };
+    let (divisor, basis_weight) = match basis {
+        Basis::Weights => (1., 0.),
+        Basis::Flour => (flour, flour),
+        Basis::Dough => (flour, weighed_total(weights.iter().cloned())),
+    };
+    ingredients.set_vec(weights.into_iter()
+        .map(|ingredient| match ingredient.kind {
+            IngredientKind::Weighed => Ingredient { amount: ingredient.amount / divisor, ..ingredient },
+            _ => ingredient,
+        })
+        .collect::<Vec<_>>());
+    basis_weight
+}
+
+fn join_sections(outer: &str, inner: &str) -> SharedString {
+    match (outer, inner) {
+        ("", inner) => inner.into(),
+        (outer, "") => outer.into(),
+        (outer, inner) => format!("{} · {}", outer, inner).into(),
+    }
+}
+
+fn expand_into(
+    recipe: &Recipe,
+    recipes: &[Recipe],
+    factor: f32,
+    section: &str,
+    depth: usize,
+    ingredients: &mut Vec<Ingredient>,
+    steps: &mut Vec<Step>,
+) {
+    let base = ingredients.len() as i32;
+    let mass_factor = mass_factor(recipe);
+    ingredients.extend(recipe.ingredients.iter().map(|ingredient| Ingredient {
+        amount: match ingredient.kind {
+            IngredientKind::Weighed => ingredient.amount * factor * mass_factor,
+            _ => ingredient.amount * factor,
+        },
+        section: join_sections(section, &ingredient.section),
+        ..ingredient
+    }));
+    steps.extend(recipe.steps.iter().map(|step| match step.kind {
+        StepKind::Weigh => Step { ingredient: step.ingredient + base, ..step },
+        _ => step,
+    }));
+    if depth == MAX_DEPTH {
+        return;
+    }
+    for component in recipe.components.iter() {
+        if let Some(sub) = recipes.iter().find(|sub| sub.name == component.recipe) {
+            let sub_section = join_sections(section, &sub.name);
+            expand_into(sub, recipes, factor * component.factor, &sub_section, depth + 1, ingredients, steps);
+        }
+    }
+}
+
+/// Fills in each recipe's components, scaled and in their own
+/// sections, after its own ingredients and steps, and works out
+/// baker's percentages as weights. Components that don't name an
+/// existing recipe are left out.
+pub fn expand(recipes: &[Recipe]) -> Vec<Recipe> {
+    recipes.iter()
+        .map(|recipe| {
+            let mut ingredients = Vec::new();
+            let mut steps = Vec::new();
+            expand_into(recipe, recipes, 1., "", 0, &mut ingredients, &mut steps);
+            recipe_with_steps(&recipe.name, ingredients, steps)
+        })
+        .collect()
+}
+
+/// Whether two recipes have the same ingredients, so progress on one
+/// still makes sense for the other.
+pub fn same_ingredients(a: &Recipe, b: &Recipe) -> bool {
+    a.ingredients.iter().eq(b.ingredients.iter())
+}
+
+const VERSION: u32 = 1;
+
+fn kind_to_u8(kind: IngredientKind) -> u8 {
+    match kind {
+        IngredientKind::Weighed => 0,
+        IngredientKind::Counted => 1,
+        IngredientKind::Teaspoons => 2,
+        IngredientKind::Tablespoons => 3,
+        IngredientKind::Cups => 4,
+    }
+}
+
+fn kind_from_u8(val: u8) -> Option<IngredientKind> {
+    Some(match val {
+        0 => IngredientKind::Weighed,
+        1 => IngredientKind::Counted,
+        2 => IngredientKind::Teaspoons,
+        3 => IngredientKind::Tablespoons,
+        4 => IngredientKind::Cups,
+        _ => return None,
+    })
+}
+
+fn role_to_u8(role: IngredientRole) -> u8 {
+    match role {
+        IngredientRole::Other => 0,
+        IngredientRole::Flour => 1,
+        IngredientRole::Liquid => 2,
+    }
+}
+
+fn role_from_u8(val: u8) -> Option<IngredientRole> {
+    Some(match val {
+        0 => IngredientRole::Other,
+        1 => IngredientRole::Flour,
+        2 => IngredientRole::Liquid,
+        _ => return None,
+    })
+}
+
+fn basis_to_u8(basis: Basis) -> u8 {
+    match basis {
+        Basis::Weights => 0,
+        Basis::Flour => 1,
+        Basis::Dough => 2,
+    }
+}
+
+fn basis_from_u8(val: u8) -> Option<Basis> {
+    Some(match val {
+        0 => Basis::Weights,
+        1 => Basis::Flour,
+        2 => Basis::Dough,
+        _ => return None,
+    })
+}
+
+fn step_kind_to_u8(kind: StepKind) -> u8 {
+    match kind {
+        StepKind::Weigh => 0,
+        StepKind::Instruction => 1,
+        StepKind::Timer => 2,
+    }
+}
+
+fn step_kind_from_u8(val: u8) -> Option<StepKind> {
+    Some(match val {
+        0 => StepKind::Weigh,
+        1 => StepKind::Instruction,
+        2 => StepKind::Timer,
+        _ => return None,
+    })
+}
+
+fn encode(recipes: &[Recipe]) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.u32(recipes.len() as u32);
+    for recipe in recipes {
+        encoder.str(&recipe.name);
+        encoder.u32(recipe.ingredients.row_count() as u32);
+        for ingredient in recipe.ingredients.iter() {
+            encoder.str(&ingredient.name);
+            encoder.f32(ingredient.amount);
+            encoder.u8(kind_to_u8(ingredient.kind));
+            encoder.str(&ingredient.section);
+            encoder.u8(role_to_u8(ingredient.role));
+            encoder.f32(ingredient.tolerance);
+            encoder.str(&ingredient.entry);
+        }
+        encoder.u32(recipe.steps.row_count() as u32);
+        for step in recipe.steps.iter() {
+            encoder.u8(step_kind_to_u8(step.kind));
+            encoder.u32(step.ingredient as u32);
+            encoder.str(&step.text);
+            encoder.u32(step.seconds as u32);
+        }
+        encoder.u32(recipe.components.row_count() as u32);
+        for component in recipe.components.iter() {
+            encoder.str(&component.recipe);
+            encoder.f32(component.factor);
+        }
+        encoder.u8(basis_to_u8(recipe.basis));
+        encoder.f32(recipe.basis_weight);
+        encoder.str(&recipe.tags);
+        encoder.u8(recipe.favorite as u8);
+    }
+    encoder.finish()
+}
+
+fn decode(data: &[u8]) -> Option<Vec<Recipe>> {
+    let mut decoder = Decoder::new(data);
+    if decoder.u32()? != VERSION {
+        return None;
+    }
+    let count = decoder.u32()?;
+    let mut recipes = Vec::new();
+    for _ in 0..count {
+        let name = decoder.str()?;
+        let ingredient_count = decoder.u32()?;
+        let mut ingredients = Vec::new();
+        for _ in 0..ingredient_count {
+            let name = decoder.str()?;
+            let amount = decoder.f32()?;
+            let kind = kind_from_u8(decoder.u8()?)?;
+            let section = decoder.str()?;
+            let role = role_from_u8(decoder.u8()?)?;
+            let tolerance = decoder.f32()?;
+            let entry = decoder.str()?;
+            ingredients.push(Ingredient {
+                section: section.into(),
+                role,
+                tolerance,
+                entry: entry.into(),
+                ..measured(name, amount, kind)
+            });
+        }
+        let step_count = decoder.u32()?;
+        let mut steps = Vec::new();
+        for _ in 0..step_count {
+            let kind = step_kind_from_u8(decoder.u8()?)?;
+            let ingredient = decoder.u32()? as i32;
+            let text = decoder.str()?;
+            let seconds = decoder.u32()? as i32;
+            steps.push(Step { kind, ingredient, text: text.into(), seconds });
+        }
+        let mut components = Vec::new();
+        for _ in 0..decoder.u32()? {
+            let name = decoder.str()?;
+            components.push(component(name, decoder.f32()?));
+        }
+        let basis = basis_from_u8(decoder.u8()?)?;
+        let basis_weight = decoder.f32()?;
+        let tags = decoder.str()?;
+        let favorite = decoder.u8()? != 0;
+        recipes.push(Recipe {
+            components: ModelRc::new(VecModel::from(components)),
+            basis,
+            basis_weight,
+            tags: tags.into(),
+            favorite,
+            ..recipe_with_steps(name, ingredients, steps)
+        });
+    }
+    Some(recipes)
+}
+
+/// Loads the saved recipes, falling back to the built-in ones if
+/// nothing has been saved yet.
+pub fn load<F: Storage>(blob: &Blob<F>) -> Vec<Recipe> {
+    blob.load().and_then(|data| decode(&data)).unwrap_or_else(defaults)
+}
+
+/// Whether two recipes would be saved the same, i.e. whether anything
+/// got edited.
+pub fn same_recipe(a: &Recipe, b: &Recipe) -> bool {
+    encode(slice::from_ref(a)) == encode(slice::from_ref(b))
+}
+
+pub fn save<F: Storage>(blob: &Blob<F>, recipes: &[Recipe]) -> Result<(), &'static str> {
+    blob.save(&encode(recipes))
+}
+
+const RECENT_VERSION: u32 = 1;
+
+/// The names of the recipes opened lately, newest first, or none if
+/// nothing's been saved yet.
+pub fn load_recent<F: Storage>(blob: &Blob<F>) -> Vec<String> {
+    let decode = |data: &[u8]| {
+        let mut decoder = Decoder::new(data);
+        if decoder.u32()? != RECENT_VERSION {
+            return None;
+        }
+        (0..decoder.u32()?).map(|_| Some(decoder.str()?.into())).collect::<Option<Vec<String>>>()
+    };
+    blob.load().and_then(|data| decode(&data)).unwrap_or_default()
+}
+
+pub fn save_recent<F: Storage>(blob: &Blob<F>, recent: &[String]) -> Result<(), &'static str> {
+    let mut encoder = Encoder::default();
+    encoder.u32(RECENT_VERSION);
+    encoder.u32(recent.len() as u32);
+    for name in recent {
+        encoder.str(name);
+    }
+    blob.save(&encoder.finish())
+}
@@ -0,0 +1,176 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+use core::mem;
+
+use embedded_hal_1::delay::DelayNs;
+use embedded_hal_1::i2c::I2c as I2cTrait;
+
+use nau7802::{AfeCalibrationStatus, Nau7802};
+
+pub const ONE_KG: f32 = 1.0 / 674500.0;
+
+/// Weight of the newest raw sample in the exponential moving average.
+const FILTER_ALPHA: f32 = 0.2;
+
+#[derive(Default)]
+enum LoadCell<I: I2cTrait> {
+    #[default]
+    Empty,
+    Unconnected(I),
+    Calibrating(Nau7802<I>),
+    Running { adc: Nau7802<I>, val: i32 },
+}
+
+impl<I: I2cTrait> LoadCell<I> {
+    fn step_inner(self, wait: &mut impl DelayNs) -> (Self, Option<i32>) {
+        use LoadCell::*;
+        match self {
+            Empty =>
+                // this shouldn't happen :)
+                (Empty, None),
+            Unconnected(i2c) =>
+                match Nau7802::new(i2c, wait) {
+                    Ok(adc) =>
+                        (Calibrating(adc), None),
+                    Err((_, i2c)) =>
+                        (Unconnected(i2c), None),
+                },
+            Calibrating(mut adc) =>
+                if let Ok(AfeCalibrationStatus::Success) = adc.poll_afe_calibration_status() {
+                    if let Ok(val) = adc.read() {
+                        (Running { adc, val }, Some(val))
+                    } else {
+                        (Calibrating(adc), None)
+                    }
+                } else {
+                    (Calibrating(adc), None)
+                },
+            Running { mut adc, val } => {
+                let new_val = adc.read().unwrap_or(val);
+                (Running { adc, val: new_val }, Some(new_val))
+            },
+        }
+    }
+
+    /// Steps the connecting/calibrating/running state
+    /// machine. Returns the most recent raw ADC value if we have
+    /// calibrated successfully.
+    fn step(&mut self, wait: &mut impl DelayNs) -> Option<i32> {
+        let real_self = mem::take(self);
+        let (new_self, result) = real_self.step_inner(wait);
+        let _ = mem::replace(self, new_self);
+        result
+    }
+}
+
+/// Raw values this close to the ADC's 24-bit limits mean it's pinned
+/// there, and the real weight could be anything past it.
+const RAW_LIMIT: i32 = (1 << 23) - 0x1000;
+
+/// One sample out of the scale pipeline.
+#[derive(Debug, Clone, Copy)]
+pub struct Reading {
+    /// Raw ADC counts, before zeroing.
+    pub raw: i32,
+    /// Zeroed weight in kg, unfiltered.
+    pub weight: f32,
+    /// Zeroed weight in kg, after the moving average.
+    pub filtered: f32,
+    /// Filtered weight in kg from an empty scale, whatever's tared.
+    pub gross: f32,
+}
+
+impl Reading {
+    /// Whether there's more on the scale than it can measure.
+    pub fn overloaded(&self) -> bool {
+        self.raw.abs() >= RAW_LIMIT
+    }
+}
+
+/// The load cell plus everything we do to its raw values: zeroing,
+/// filtering, and optionally substituting a fake load cell fed from
+/// the serial port.
+pub struct Scale<I: I2cTrait> {
+    load_cell: LoadCell<I>,
+    fake: Option<i32>,
+    // the raw value with nothing on, and how far above that we're
+    // zeroed
+    empty: Option<i32>,
+    tare: i32,
+    val: i32,
+    filtered_val: f32,
+}
+
+impl<I: I2cTrait> Scale<I> {
+    pub fn new(i2c: I) -> Scale<I> {
+        Scale {
+            load_cell: LoadCell::Unconnected(i2c),
+            fake: None,
+            empty: None,
+            tare: 0,
+            val: 0,
+            filtered_val: 0.,
+        }
+    }
+
+    /// Steps the load cell and runs its value through the
+    /// pipeline. Returns the most recent reading once we have a value
+    /// to zero against.
+    pub fn step(&mut self, wait: &mut impl DelayNs) -> Option<Reading> {
+        let real_val = self.load_cell.step(wait);
+        let val = self.fake.or(real_val)?;
+        let empty = match self.empty {
+            Some(empty) => empty,
+            None => {
+                // first value we've seen: start the filter here so it
+                // doesn't have to climb up from nothing
+                self.empty = Some(val);
+                self.tare = 0;
+                self.filtered_val = val as f32;
+                val
+            },
+        };
+        let zero = empty + self.tare;
+        self.val = val;
+        self.filtered_val += FILTER_ALPHA * (val as f32 - self.filtered_val);
+        Some(Reading {
+            raw: val,
+            weight: ((val - zero) as f32) * ONE_KG,
+            filtered: (self.filtered_val - zero as f32) * ONE_KG,
+            gross: (self.filtered_val - empty as f32) * ONE_KG,
+        })
+    }
+
+    pub fn rezero(&mut self) {
+        if let Some(empty) = self.empty {
+            self.tare = self.val - empty;
+        }
+    }
+
+    /// Takes whatever's on the scale now to be nothing at all.
+    pub fn reset_empty(&mut self) {
+        if self.empty.is_some() {
+            self.empty = Some(self.val);
+            self.tare = 0;
+        }
+    }
+
+    /// Zeroes as if something weighing `kg` were on an empty scale,
+    /// whatever's actually on it.
+    pub fn tare_to(&mut self, kg: f32) {
+        let counts = kg / ONE_KG;
+        // `as` truncates, so nudge it to round to nearest
+        self.tare = (if counts < 0. { counts - 0.5 } else { counts + 0.5 }) as i32;
+    }
+
+    /// Feeds raw values from somewhere other than the ADC, e.g. a
+    /// replayed recording. `None` goes back to the real load cell.
+    pub fn set_fake(&mut self, val: Option<i32>) {
+        if self.fake.is_some() != val.is_some() {
+            // don't carry one load cell's tare over to the other
+            self.empty = None;
+        }
+        self.fake = val;
+    }
+}
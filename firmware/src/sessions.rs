@@ -0,0 +1,93 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Recipes that are partway through, journaled as they go so they
+//! survive the battery running out. The records themselves are up to
+//! `scale_logic::sessions`.
+
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+use scale_logic::history;
+use scale_logic::sessions::{self as record, Progress};
+use slint::{Model, ModelRc, VecModel};
+
+use crate::recipes;
+use crate::store::Journal;
+use crate::{IngredientProgress, Recipe, RecipeProgress};
+
+fn encode(name: &str, progress: &RecipeProgress) -> Vec<u8> {
+    record::encode(name, &Progress {
+        scale_factor: progress.scale_factor,
+        current_ingredient: progress.current_ingredient.max(0) as u32,
+        ingredients: progress.ingredient_progresses.iter()
+            .map(|ingredient_progress| (ingredient_progress.done, ingredient_progress.amount))
+            .collect(),
+        cumulative: progress.cumulative,
+        bowl_weight: progress.bowl_weight,
+        started: progress.started.max(0) as u32,
+        finished: progress.finished,
+    })
+}
+
+fn decode(data: &[u8]) -> Option<(&str, RecipeProgress)> {
+    let (name, progress) = record::decode(data)?;
+    let ingredient_progresses: Vec<IngredientProgress> = progress.ingredients.iter()
+        .map(|&(done, amount)| IngredientProgress { done, amount })
+        .collect();
+    Some((name, RecipeProgress {
+        scale_factor: progress.scale_factor,
+        current_ingredient: progress.current_ingredient as i32,
+        cumulative: progress.cumulative,
+        bowl_weight: progress.bowl_weight,
+        // only ever read back after a restart
+        started: history::resumed(progress.started) as i32,
+        finished: progress.finished,
+        ingredient_progresses: ModelRc::new(VecModel::from(ingredient_progresses)),
+    }))
+}
+
+// nothing's happened to it, so there's nothing to keep
+fn is_fresh(progress: &RecipeProgress) -> bool {
+    progress.scale_factor == 1.
+        && progress.current_ingredient == 0
+        && !progress.cumulative
+        && recipes::completion(progress) == 0.
+}
+
+/// Everything worth keeping, for when the journal starts over.
+fn snapshot(expanded: &VecModel<Recipe>, progresses: &VecModel<RecipeProgress>) -> Vec<Vec<u8>> {
+    expanded.iter().zip(progresses.iter())
+        .filter(|(_, progress)| !is_fresh(progress))
+        .map(|(recipe, progress)| encode(&recipe.name, &progress))
+        .collect()
+}
+
+/// Puts back whatever was saved for each recipe, as long as it still
+/// has the same number of ingredients.
+pub fn restore(records: &[Vec<u8>], expanded: &VecModel<Recipe>, progresses: &VecModel<RecipeProgress>) {
+    // later records win, since they're newer
+    for (name, progress) in records.iter().filter_map(|data| decode(data)) {
+        let Some(idx) = expanded.iter().position(|recipe| recipe.name.as_str() == name) else {
+            continue;
+        };
+        let fits = expanded.row_data(idx)
+            .is_some_and(|recipe| recipe.ingredients.row_count() == progress.ingredient_progresses.row_count());
+        if fits {
+            progresses.set_row_data(idx, progress);
+        }
+    }
+}
+
+/// Journals one recipe's progress.
+pub fn save<F: NorFlash>(
+    journal: &mut Journal<F>,
+    expanded: &VecModel<Recipe>,
+    progresses: &VecModel<RecipeProgress>,
+    idx: usize,
+) -> Result<(), &'static str> {
+    let (Some(recipe), Some(progress)) = (expanded.row_data(idx), progresses.row_data(idx)) else {
+        return Ok(());
+    };
+    journal.append(&encode(&recipe.name, &progress), || snapshot(expanded, progresses))
+}
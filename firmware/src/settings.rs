@@ -0,0 +1,50 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Preferences that should stay the way they were left across restarts.
+
+use alloc::vec::Vec;
+
+use embedded_storage::Storage;
+
+use scale_logic::buzzer;
+
+use crate::store::{Blob, Decoder, Encoder};
+use crate::Settings;
+
+// v2 added recognizing containers
+const VERSION: u32 = 2;
+
+pub fn defaults() -> Settings {
+    Settings { volume: buzzer::DEFAULT_VOLUME as i32, muted: false, recognize_containers: false }
+}
+
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.u8(settings.volume.clamp(0, buzzer::MAX_VOLUME as i32) as u8);
+    encoder.u8(settings.muted as u8);
+    encoder.u8(settings.recognize_containers as u8);
+    encoder.finish()
+}
+
+fn decode(data: &[u8]) -> Option<Settings> {
+    let mut decoder = Decoder::new(data);
+    let version = decoder.u32()?;
+    if version > VERSION {
+        return None;
+    }
+    let volume = decoder.u8()?.min(buzzer::MAX_VOLUME) as i32;
+    let muted = decoder.u8()? != 0;
+    let recognize_containers = version >= 2 && decoder.u8()? != 0;
+    Some(Settings { volume, muted, recognize_containers })
+}
+
+/// Loads the saved settings, or the defaults if there aren't any.
+pub fn load<F: Storage>(blob: &Blob<F>) -> Settings {
+    blob.load().and_then(|data| decode(&data)).unwrap_or_else(defaults)
+}
+
+pub fn save<F: Storage>(blob: &Blob<F>, settings: &Settings) -> Result<(), &'static str> {
+    blob.save(&encode(settings))
+}
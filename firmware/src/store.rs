@@ -0,0 +1,37 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Where everything's kept in the external flash. How it's kept there
+//! is up to `scale_logic::store`.
+
+pub use scale_logic::store::{Blob, Decoder, Encoder, Journal, Log, Region};
+
+// The app lives in the first few MB of the 16MB flash, so keep our
+// data well out of its way. Blobs get a second copy each, and those
+// start at 0x0082_2000, after everything else.
+pub const RECIPES: [Region; 2] = [
+    Region { offset: 0x0080_0000, size: 0x0001_0000 },
+    Region { offset: 0x0082_2000, size: 0x0001_0000 },
+];
+pub const SETTINGS: [Region; 2] = [
+    Region { offset: 0x0081_0000, size: 0x0000_1000 },
+    Region { offset: 0x0083_2000, size: 0x0000_1000 },
+];
+pub const SESSIONS: Region = Region { offset: 0x0081_1000, size: 0x0000_4000 };
+pub const CONTAINERS: [Region; 2] = [
+    Region { offset: 0x0081_5000, size: 0x0000_1000 },
+    Region { offset: 0x0083_3000, size: 0x0000_1000 },
+];
+pub const INGREDIENTS: [Region; 2] = [
+    Region { offset: 0x0081_6000, size: 0x0000_2000 },
+    Region { offset: 0x0083_4000, size: 0x0000_2000 },
+];
+pub const PANTRY: [Region; 2] = [
+    Region { offset: 0x0081_8000, size: 0x0000_1000 },
+    Region { offset: 0x0083_6000, size: 0x0000_1000 },
+];
+pub const HISTORY: Region = Region { offset: 0x0081_9000, size: 0x0000_8000 };
+pub const RECENT: [Region; 2] = [
+    Region { offset: 0x0082_1000, size: 0x0000_1000 },
+    Region { offset: 0x0083_7000, size: 0x0000_1000 },
+];
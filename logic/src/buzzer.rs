@@ -0,0 +1,363 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Sounds for the piezo buzzer, queued up and stepped through from the
+//! main loop so nothing ever waits on them.
+//!
+//! Whatever actually drives the buzzer only has to implement [`Pwm`].
+
+/// The pitches the buzzer can play. The hardware keeps a PWM timer
+/// running for each, so there are only a few; these sit up around
+/// where the piezo is loudest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pitch {
+    C7,
+    E7,
+    G7,
+    C8,
+}
+
+impl Pitch {
+    pub const ALL: [Pitch; 4] = [Pitch::C7, Pitch::E7, Pitch::G7, Pitch::C8];
+
+    pub fn hz(self) -> u32 {
+        match self {
+            Pitch::C7 => 2093,
+            Pitch::E7 => 2637,
+            Pitch::G7 => 3136,
+            Pitch::C8 => 4186,
+        }
+    }
+}
+
+/// Something that can make a square wave on the buzzer's pin.
+pub trait Pwm {
+    fn tone(&mut self, pitch: Pitch, duty_pct: u8);
+    fn quiet(&mut self);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Click,
+    InRange,
+    Over,
+    Overload,
+    TimerDone,
+    LowBattery,
+    Error,
+}
+
+/// A pitch (or nothing, for a rest) held for a while.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note {
+    pub pitch: Option<Pitch>,
+    pub ms: u16,
+}
+
+const fn tone(pitch: Pitch, ms: u16) -> Note {
+    Note { pitch: Some(pitch), ms }
+}
+
+const fn rest(ms: u16) -> Note {
+    Note { pitch: None, ms }
+}
+
+use Pitch::*;
+
+const CLICK: &[Note] = &[tone(C8, 8)];
+const IN_RANGE: &[Note] = &[tone(E7, 40), tone(C8, 60)];
+const OVER: &[Note] = &[tone(C8, 120), rest(80), tone(C8, 120), rest(80), tone(C8, 120)];
+const OVERLOAD: &[Note] = &[tone(C7, 400), rest(100), tone(C7, 400)];
+const TIMER_DONE: &[Note] = &[
+    tone(C7, 150), tone(E7, 150), tone(G7, 150), tone(C8, 300), rest(200),
+    tone(C7, 150), tone(E7, 150), tone(G7, 150), tone(C8, 300),
+];
+const LOW_BATTERY: &[Note] = &[tone(G7, 150), tone(E7, 150), tone(C7, 300)];
+const ERROR: &[Note] = &[tone(C7, 100), rest(50), tone(C7, 300)];
+
+pub fn notes(sound: Sound) -> &'static [Note] {
+    match sound {
+        Sound::Click => CLICK,
+        Sound::InRange => IN_RANGE,
+        Sound::Over => OVER,
+        Sound::Overload => OVERLOAD,
+        Sound::TimerDone => TIMER_DONE,
+        Sound::LowBattery => LOW_BATTERY,
+        Sound::Error => ERROR,
+    }
+}
+
+pub const MAX_VOLUME: u8 = 5;
+pub const DEFAULT_VOLUME: u8 = 3;
+// a piezo is loudest at half duty, and gets quieter either side of it
+const DUTY_PCT: [u8; MAX_VOLUME as usize + 1] = [0, 3, 6, 12, 25, 50];
+
+const QUEUE_LEN: usize = 4;
+
+struct Playing {
+    notes: &'static [Note],
+    idx: usize,
+    ends_ms: u64,
+}
+
+/// Plays sounds one after another on a [`Pwm`].
+pub struct Sequencer<P> {
+    pwm: P,
+    volume: u8,
+    muted: bool,
+    queue: [Sound; QUEUE_LEN],
+    queued: usize,
+    playing: Option<Playing>,
+}
+
+impl<P: Pwm> Sequencer<P> {
+    pub fn new(pwm: P) -> Sequencer<P> {
+        Sequencer {
+            pwm,
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            queue: [Sound::Click; QUEUE_LEN],
+            queued: 0,
+            playing: None,
+        }
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    /// Takes effect from the next note on.
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(MAX_VOLUME);
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Muting cuts off anything playing or queued, too.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        if muted {
+            self.queued = 0;
+            if self.playing.take().is_some() {
+                self.pwm.quiet();
+            }
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.playing.is_none() && self.queued == 0
+    }
+
+    /// Queues a sound to start on the next [`poll`](Self::poll).
+    /// Clicks are only worth anything straight away, so they're
+    /// dropped if anything else is going; so is anything that doesn't
+    /// fit in the queue.
+    pub fn play(&mut self, sound: Sound) {
+        if self.muted || (sound == Sound::Click && !self.is_idle()) || self.queued == QUEUE_LEN {
+            return;
+        }
+        self.queue[self.queued] = sound;
+        self.queued += 1;
+    }
+
+    /// Moves on to the next note once the current one is up. Each note
+    /// lasts from the poll that starts it, so a slow main loop
+    /// stretches sounds rather than skipping bits of them.
+    pub fn poll(&mut self, now_ms: u64) {
+        if self.playing.as_ref().is_some_and(|playing| now_ms < playing.ends_ms) {
+            return;
+        }
+        let finished = self.playing.take();
+        let next = match finished {
+            Some(Playing { notes, idx, .. }) if idx + 1 < notes.len() => Some((notes, idx + 1)),
+            _ => self.dequeue().map(|sound| (notes(sound), 0)),
+        };
+        match next {
+            Some((notes, idx)) => {
+                self.start(notes[idx]);
+                self.playing = Some(Playing { notes, idx, ends_ms: now_ms + notes[idx].ms as u64 });
+            },
+            None if finished.is_some() => self.pwm.quiet(),
+            None => (),
+        }
+    }
+
+    fn dequeue(&mut self) -> Option<Sound> {
+        if self.queued == 0 {
+            return None;
+        }
+        let sound = self.queue[0];
+        self.queue.copy_within(1..self.queued, 0);
+        self.queued -= 1;
+        Some(sound)
+    }
+
+    fn start(&mut self, note: Note) {
+        match note.pitch {
+            Some(pitch) if self.volume > 0 => self.pwm.tone(pitch, DUTY_PCT[self.volume as usize]),
+            _ => self.pwm.quiet(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Event {
+        Tone(Pitch, u8),
+        Quiet,
+    }
+
+    #[derive(Default)]
+    struct FakePwm {
+        events: Vec<Event>,
+    }
+
+    impl Pwm for FakePwm {
+        fn tone(&mut self, pitch: Pitch, duty_pct: u8) {
+            self.events.push(Event::Tone(pitch, duty_pct));
+        }
+
+        fn quiet(&mut self) {
+            self.events.push(Event::Quiet);
+        }
+    }
+
+    fn sequencer() -> Sequencer<FakePwm> {
+        Sequencer::new(FakePwm::default())
+    }
+
+    // polls every ms from `from` up to but not including `to`
+    fn run(sequencer: &mut Sequencer<FakePwm>, from: u64, to: u64) {
+        for now in from..to {
+            sequencer.poll(now);
+        }
+    }
+
+    const LOUD: u8 = DUTY_PCT[DEFAULT_VOLUME as usize];
+
+    #[test]
+    fn nothing_happens_until_polled() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::InRange);
+        assert!(sequencer.pwm.events.is_empty());
+        sequencer.poll(0);
+        assert_eq!(sequencer.pwm.events, [Event::Tone(E7, LOUD)]);
+    }
+
+    #[test]
+    fn plays_notes_for_their_length() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::InRange);
+        run(&mut sequencer, 0, 40);
+        assert_eq!(sequencer.pwm.events, [Event::Tone(E7, LOUD)]);
+        run(&mut sequencer, 40, 100);
+        assert_eq!(sequencer.pwm.events, [Event::Tone(E7, LOUD), Event::Tone(C8, LOUD)]);
+        sequencer.poll(100);
+        assert_eq!(sequencer.pwm.events, [Event::Tone(E7, LOUD), Event::Tone(C8, LOUD), Event::Quiet]);
+        assert!(sequencer.is_idle());
+        run(&mut sequencer, 101, 200);
+        assert_eq!(sequencer.pwm.events.len(), 3);
+    }
+
+    #[test]
+    fn rests_are_quiet() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::Error);
+        run(&mut sequencer, 0, 1000);
+        assert_eq!(sequencer.pwm.events, [
+            Event::Tone(C7, LOUD),
+            Event::Quiet,
+            Event::Tone(C7, LOUD),
+            Event::Quiet,
+        ]);
+    }
+
+    #[test]
+    fn slow_polls_stretch_rather_than_skip() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::Over);
+        for now in (0..3000).step_by(500) {
+            sequencer.poll(now);
+        }
+        let tones = sequencer.pwm.events.iter().filter(|event| **event != Event::Quiet).count();
+        assert_eq!(tones, 3);
+    }
+
+    #[test]
+    fn sounds_queue_up() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::Click);
+        sequencer.play(Sound::LowBattery);
+        run(&mut sequencer, 0, 1000);
+        assert_eq!(sequencer.pwm.events, [
+            Event::Tone(C8, LOUD),
+            Event::Tone(G7, LOUD),
+            Event::Tone(E7, LOUD),
+            Event::Tone(C7, LOUD),
+            Event::Quiet,
+        ]);
+    }
+
+    #[test]
+    fn clicks_only_when_idle() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::Error);
+        sequencer.play(Sound::Click);
+        run(&mut sequencer, 0, 1000);
+        assert!(!sequencer.pwm.events.contains(&Event::Tone(C8, LOUD)));
+    }
+
+    #[test]
+    fn full_queue_drops() {
+        let mut sequencer = sequencer();
+        for _ in 0..QUEUE_LEN + 2 {
+            sequencer.play(Sound::Error);
+        }
+        run(&mut sequencer, 0, 10_000);
+        let tones = sequencer.pwm.events.iter().filter(|event| **event != Event::Quiet).count();
+        assert_eq!(tones, QUEUE_LEN * 2);
+    }
+
+    #[test]
+    fn volume_sets_duty() {
+        let mut sequencer = sequencer();
+        sequencer.set_volume(MAX_VOLUME + 3);
+        assert_eq!(sequencer.volume(), MAX_VOLUME);
+        sequencer.play(Sound::Click);
+        sequencer.poll(0);
+        assert_eq!(sequencer.pwm.events, [Event::Tone(C8, 50)]);
+    }
+
+    #[test]
+    fn volume_zero_keeps_time_quietly() {
+        let mut sequencer = sequencer();
+        sequencer.set_volume(0);
+        sequencer.play(Sound::InRange);
+        sequencer.poll(0);
+        assert!(!sequencer.is_idle());
+        run(&mut sequencer, 1, 200);
+        assert!(sequencer.pwm.events.iter().all(|event| *event == Event::Quiet));
+    }
+
+    #[test]
+    fn muting_cuts_off_and_drops() {
+        let mut sequencer = sequencer();
+        sequencer.play(Sound::TimerDone);
+        sequencer.poll(0);
+        sequencer.set_muted(true);
+        assert_eq!(sequencer.pwm.events, [Event::Tone(C7, LOUD), Event::Quiet]);
+        sequencer.play(Sound::Error);
+        run(&mut sequencer, 1, 1000);
+        assert_eq!(sequencer.pwm.events.len(), 2);
+        assert!(sequencer.is_idle());
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! How the recipe list is laid out once there are too many recipes to
+//! scroll through one by one: recent ones and favorites up top, a
+//! folder for each tag, then everything from A to Z.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// How many recently opened recipes to keep.
+pub const RECENT: usize = 3;
+
+/// What the list needs to know about a recipe.
+#[derive(Debug, Clone, Copy)]
+pub struct Meta<'a> {
+    pub name: &'a str,
+    /// Comma-separated, e.g. "bread, weekday".
+    pub tags: &'a str,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Just a label, which can't be selected.
+    Heading,
+    Folder,
+    /// Back out of a folder.
+    Back,
+    Recipe,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub kind: Kind,
+    /// The heading, or the folder's name.
+    pub label: String,
+    /// The recipe's index, for recipe rows.
+    pub recipe: usize,
+    /// What the recipe's filed under, for rows in alphabetical runs:
+    /// its first letter, or '#' for anything else.
+    pub letter: Option<char>,
+    /// How many recipes are in a folder.
+    pub count: usize,
+}
+
+impl Row {
+    fn heading(label: &str) -> Row {
+        Row { kind: Kind::Heading, label: label.into(), recipe: 0, letter: None, count: 0 }
+    }
+
+    fn recipe(recipe: usize, letter: Option<char>) -> Row {
+        Row { kind: Kind::Recipe, label: String::new(), recipe, letter, count: 0 }
+    }
+}
+
+/// The tags in a recipe's tag list, trimmed, with empty ones left out.
+pub fn tags(tags: &str) -> impl Iterator<Item = &str> {
+    tags.split(',').map(str::trim).filter(|tag| !tag.is_empty())
+}
+
+fn letter(name: &str) -> char {
+    match name.chars().find(|c| !c.is_whitespace()) {
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase(),
+        _ => '#',
+    }
+}
+
+fn by_name(recipes: &[Meta], mut indices: Vec<usize>) -> Vec<usize> {
+    indices.sort_by_cached_key(|&idx| recipes[idx].name.to_lowercase());
+    indices
+}
+
+fn alphabetical<'a>(recipes: &'a [Meta], indices: Vec<usize>) -> impl Iterator<Item = Row> + 'a {
+    by_name(recipes, indices).into_iter().map(|idx| Row::recipe(idx, Some(letter(recipes[idx].name))))
+}
+
+/// The rows for a folder, or for the top with "". Sections with nothing
+/// in them are left out.
+pub fn rows(recipes: &[Meta], recent: &[String], folder: &str) -> Vec<Row> {
+    let mut rows = Vec::new();
+    if !folder.is_empty() {
+        rows.push(Row { kind: Kind::Back, label: folder.into(), recipe: 0, letter: None, count: 0 });
+        let inside = (0..recipes.len()).filter(|&idx| tags(recipes[idx].tags).any(|tag| tag == folder)).collect();
+        rows.extend(alphabetical(recipes, inside));
+        return rows;
+    }
+
+    let recent: Vec<usize> = recent.iter()
+        .filter_map(|name| recipes.iter().position(|recipe| recipe.name == name))
+        .take(RECENT)
+        .collect();
+    if !recent.is_empty() {
+        rows.push(Row::heading("recent"));
+        rows.extend(recent.into_iter().map(|idx| Row::recipe(idx, None)));
+    }
+
+    let favorites: Vec<usize> = (0..recipes.len()).filter(|&idx| recipes[idx].favorite).collect();
+    if !favorites.is_empty() {
+        rows.push(Row::heading("favorites"));
+        rows.extend(by_name(recipes, favorites).into_iter().map(|idx| Row::recipe(idx, None)));
+    }
+
+    let mut folders: Vec<(String, usize)> = Vec::new();
+    for recipe in recipes {
+        for tag in tags(recipe.tags) {
+            match folders.iter_mut().find(|(name, _)| name == tag) {
+                Some((_, count)) => *count += 1,
+                None => folders.push((tag.into(), 1)),
+            }
+        }
+    }
+    if !folders.is_empty() {
+        folders.sort_by_cached_key(|(name, _)| name.to_lowercase());
+        rows.push(Row::heading("folders"));
+        rows.extend(folders.into_iter()
+            .map(|(label, count)| Row { kind: Kind::Folder, label, recipe: 0, letter: None, count }));
+    }
+
+    // only worth a heading if there's something above it
+    if !rows.is_empty() {
+        rows.push(Row::heading("A–Z"));
+    }
+    rows.extend(alphabetical(recipes, (0..recipes.len()).collect()));
+    rows
+}
+
+/// The next row in a direction (1 or -1) from `from` that can be
+/// selected, or -1 or `rows.len()` if we run off the end.
+pub fn skip(rows: &[Row], from: i32, direction: i32) -> i32 {
+    let mut row = from + direction;
+    while row >= 0 && (row as usize) < rows.len() && rows[row as usize].kind == Kind::Heading {
+        row += direction;
+    }
+    row.clamp(-1, rows.len() as i32)
+}
+
+/// Where a fast spin from `from` lands: the first recipe under the
+/// next letter along, or the start of this letter going backwards if
+/// we're not at it already. Past the last letter, and on rows that
+/// aren't filed under one, it just steps along like `skip`.
+pub fn jump(rows: &[Row], from: i32, direction: i32) -> i32 {
+    let letter_at = |row: i32| rows.get(row as usize).and_then(|row| row.letter);
+    let Some(letter) = letter_at(from) else {
+        return skip(rows, from, direction);
+    };
+    if direction > 0 {
+        let mut row = from;
+        while letter_at(row) == Some(letter) {
+            row += 1;
+        }
+        return if letter_at(row).is_some() { row } else { skip(rows, row - 1, direction) };
+    }
+    let start = |mut row: i32| {
+        let letter = letter_at(row);
+        while row > 0 && letter_at(row - 1).is_some() && letter_at(row - 1) == letter {
+            row -= 1;
+        }
+        row
+    };
+    let here = start(from);
+    if here != from {
+        here
+    } else if letter_at(from - 1).is_none() {
+        skip(rows, from, direction)
+    } else {
+        start(from - 1)
+    }
+}
+
+/// The row showing a recipe: `near` if it still does, otherwise the
+/// closest one that does.
+pub fn row_of(rows: &[Row], recipe: usize, near: usize) -> Option<usize> {
+    let shows = |row: &Row| row.kind == Kind::Recipe && row.recipe == recipe;
+    (0..rows.len())
+        .filter(|&row| shows(&rows[row]))
+        .min_by_key(|&row| row.abs_diff(near))
+}
+
+/// Puts a recipe at the top of the recent ones.
+pub fn opened(recent: &mut Vec<String>, name: &str) {
+    recent.retain(|other| other != name);
+    recent.insert(0, name.into());
+    recent.truncate(RECENT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn meta<'a>(name: &'a str, tags: &'a str, favorite: bool) -> Meta<'a> {
+        Meta { name, tags, favorite }
+    }
+
+    fn library() -> Vec<Meta<'static>> {
+        vec![
+            meta("Focaccia", "bread", false),
+            meta("choux", "pastry", true),
+            meta("Babka", "bread, sweet", false),
+            meta("Brioche", "bread,sweet", true),
+            meta("Cream Puffs", "pastry", false),
+            meta("50/50 Loaf", "", false),
+        ]
+    }
+
+    #[test]
+    fn lays_out_the_top() {
+        let recipes = library();
+        let rows = rows(&recipes, &["Babka".into(), "gone".into()], "");
+        let kinds: Vec<Kind> = rows.iter().map(|row| row.kind).collect();
+        assert_eq!(kinds[..9], [
+            Kind::Heading, Kind::Recipe, // recent
+            Kind::Heading, Kind::Recipe, Kind::Recipe, // favorites
+            Kind::Heading, Kind::Folder, Kind::Folder, Kind::Folder,
+        ]);
+        assert_eq!(rows[1].recipe, 2);
+        // favorites go alphabetically too
+        assert_eq!((rows[3].recipe, rows[4].recipe), (3, 1));
+        assert_eq!(rows[6].label, "bread");
+        assert_eq!(rows[6].count, 3);
+        assert_eq!(rows[8].label, "sweet");
+        assert_eq!(rows[9].label, "A–Z");
+        let az: Vec<(usize, Option<char>)> = rows[10..].iter().map(|row| (row.recipe, row.letter)).collect();
+        assert_eq!(az, [(5, Some('#')), (2, Some('B')), (3, Some('B')), (1, Some('C')), (4, Some('C')), (0, Some('F'))]);
+    }
+
+    #[test]
+    fn plain_list_without_metadata() {
+        let recipes = [meta("b", "", false), meta("a", "", false)];
+        let rows = rows(&recipes, &[], "");
+        assert!(rows.iter().all(|row| row.kind == Kind::Recipe));
+        assert_eq!(rows[0].recipe, 1);
+    }
+
+    #[test]
+    fn opens_folders() {
+        let recipes = library();
+        let rows = rows(&recipes, &[], "sweet");
+        assert_eq!(rows[0].kind, Kind::Back);
+        assert!(rows[1..].iter().map(|row| row.recipe).eq([2, 3]));
+    }
+
+    #[test]
+    fn moves_around() {
+        let recipes = library();
+        let rows = rows(&recipes, &[], "");
+        // favorites, folders, A–Z
+        assert_eq!(skip(&rows, -1, 1), 1);
+        assert_eq!(skip(&rows, 2, 1), 4);
+        assert_eq!(skip(&rows, 4, -1), 2);
+        assert_eq!(skip(&rows, 1, -1), -1);
+        assert_eq!(skip(&rows, rows.len() as i32 - 1, 1), rows.len() as i32);
+        // A–Z starts at 8: # B B C C F
+        assert_eq!(jump(&rows, 8, 1), 9);
+        assert_eq!(jump(&rows, 9, 1), 11);
+        assert_eq!(jump(&rows, 13, 1), 14);
+        assert_eq!(jump(&rows, 12, -1), 11);
+        assert_eq!(jump(&rows, 11, -1), 9);
+        assert_eq!(jump(&rows, 8, -1), 6);
+        assert_eq!(jump(&rows, 4, 1), 5);
+        assert_eq!(row_of(&rows, 3, 10), Some(10));
+        assert_eq!(row_of(&rows, 3, 0), Some(1));
+    }
+
+    #[test]
+    fn remembers_recent() {
+        let mut recent = Vec::new();
+        for name in ["a", "b", "c", "a", "d"] {
+            opened(&mut recent, name);
+        }
+        assert_eq!(recent, ["d", "a", "c"]);
+    }
+}
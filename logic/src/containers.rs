@@ -0,0 +1,90 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Telling which bowl just went on the scale from what it weighs.
+
+use crate::settle::Settle;
+
+/// How far off a container's saved weight it can weigh and still be
+/// that container: a couple of grams, or 1% for the heavy ones.
+pub fn tolerance(tare: f32) -> f32 {
+    (tare * 0.01).max(0.002)
+}
+
+/// The container whose empty weight is closest to `weight`, if any are
+/// close enough.
+pub fn recognize(tares: impl Iterator<Item = f32>, weight: f32) -> Option<usize> {
+    tares
+        .enumerate()
+        .map(|(idx, tare)| (idx, (weight - tare).abs(), tolerance(tare)))
+        .filter(|&(_, off, tolerance)| off <= tolerance)
+        .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+        .map(|(idx, _, _)| idx)
+}
+
+/// Anything lighter than this is nothing at all.
+pub const EMPTY: f32 = 0.002;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// Something went onto the empty scale and settled at this weight.
+    Placed(f32),
+    /// The scale's empty again.
+    Emptied,
+}
+
+/// Watches the weight on the scale, as measured from empty, for it
+/// settling somewhere new: going from nothing to something, or back.
+/// Pouring things into whatever's already there doesn't count.
+#[derive(Default)]
+pub struct Watcher {
+    settle: Settle,
+    // the last time it settled, it was on nothing
+    empty: bool,
+}
+
+impl Watcher {
+    pub fn step(&mut self, gross: f32, now_ms: u64) -> Option<Change> {
+        let settled = self.settle.step(gross, now_ms)?;
+        let empty = settled.abs() < EMPTY;
+        match (self.empty, empty) {
+            (false, true) => {
+                self.empty = true;
+                Some(Change::Emptied)
+            },
+            (true, false) => {
+                self.empty = false;
+                Some(Change::Placed(settled))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settle::hold;
+
+    #[test]
+    fn closest_within_tolerance() {
+        let tares = [0.350, 0.352, 1.200];
+        assert_eq!(recognize(tares.into_iter(), 0.3515), Some(1));
+        assert_eq!(recognize(tares.into_iter(), 0.349), Some(0));
+        // 1% of 1.2kg is 12g
+        assert_eq!(recognize(tares.into_iter(), 1.210), Some(2));
+        assert_eq!(recognize(tares.into_iter(), 1.213), None);
+        assert_eq!(recognize(tares.into_iter(), 0.5), None);
+        assert_eq!(recognize([].into_iter(), 0.5), None);
+    }
+
+    #[test]
+    fn notices_a_bowl_going_on_and_off() {
+        let mut watcher = Watcher::default();
+        assert_eq!(hold(0, 1000, |now| watcher.step(0., now)), Some(Change::Emptied));
+        assert_eq!(hold(1000, 1000, |now| watcher.step(0.35, now)), Some(Change::Placed(0.35)));
+        // pouring into it isn't another bowl
+        assert_eq!(hold(2000, 1000, |now| watcher.step(0.5, now)), None);
+        assert_eq!(hold(3000, 1000, |now| watcher.step(0., now)), Some(Change::Emptied));
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Counting pieces by weight, from a sample of a few of them.
+
+use crate::settle::Settle;
+
+/// The smallest difference in weight the scale tells apart once it's
+/// settled. It's about what the display shows, too.
+pub const RESOLUTION: f32 = 0.001;
+
+/// How far off a whole number of pieces the weight can be and still be
+/// trusted to improve the estimate.
+const CLOSE: f32 = 0.2;
+
+/// Whether pieces this light can be counted at all: each one has to
+/// move the weight by a few steps, or the noise alone makes the count
+/// jump around.
+pub fn too_light(unit: f32) -> bool {
+    unit < 3. * RESOLUTION
+}
+
+/// How many pieces a weight is.
+pub fn count(weight: f32, unit: f32) -> i32 {
+    if unit <= 0. {
+        return 0;
+    }
+    let pieces = weight / unit;
+    // `as` truncates, so nudge it to round to nearest
+    (if pieces < 0. { pieces - 0.5 } else { pieces + 0.5 }) as i32
+}
+
+/// The weight of one piece, worked out from a sample and then refined
+/// as more pieces go on. Any error in the sample gets multiplied by how
+/// many there are, so whenever the weight settles on more pieces than
+/// it was last worked out from, it's worked out again from them.
+pub struct Counter {
+    unit: f32,
+    // how many pieces the unit weight's from
+    basis: u32,
+    settle: Settle,
+}
+
+impl Counter {
+    pub fn new(sample: f32, pieces: u32) -> Counter {
+        let pieces = pieces.max(1);
+        Counter {
+            unit: sample / pieces as f32,
+            basis: pieces,
+            settle: Settle::at(sample),
+        }
+    }
+
+    pub fn unit(&self) -> f32 {
+        self.unit
+    }
+
+    /// Returns the new unit weight whenever it changes.
+    pub fn step(&mut self, weight: f32, now_ms: u64) -> Option<f32> {
+        let settled = self.settle.step(weight, now_ms)?;
+        let pieces = settled / self.unit;
+        let whole = count(settled, self.unit);
+        // no more than double at a time, so the old estimate's still
+        // good enough to be sure how many there are
+        if whole as u32 <= self.basis || whole as u32 > 2 * self.basis || (pieces - whole as f32).abs() > CLOSE {
+            return None;
+        }
+        self.basis = whole as u32;
+        self.unit = settled / whole as f32;
+        Some(self.unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settle::hold;
+
+    #[test]
+    fn counts_to_nearest() {
+        assert_eq!(count(0.0496, 0.005), 10);
+        assert_eq!(count(0.0524, 0.005), 10);
+        assert_eq!(count(-0.0051, 0.005), -1);
+        assert_eq!(count(0.05, 0.), 0);
+    }
+
+    #[test]
+    fn refines_as_more_go_on() {
+        // really 5.2g each, but the sample of ten came out light
+        let mut counter = Counter::new(0.0518, 10);
+        let unit = hold(0, 1000, |now| counter.step(0.104, now)).unwrap();
+        assert!((unit - 0.0052).abs() < 1e-6);
+        assert_eq!(counter.unit(), unit);
+        // fewer than it's from now doesn't help
+        assert_eq!(hold(1000, 1000, |now| counter.step(0.052, now)), None);
+        assert!(hold(2000, 1000, |now| counter.step(0.2081, now)).is_some());
+    }
+
+    #[test]
+    fn only_refines_when_sure() {
+        let mut counter = Counter::new(0.05, 10);
+        // 14.5 pieces: something else is on there
+        assert_eq!(hold(0, 1000, |now| counter.step(0.0725, now)), None);
+        // too many more at once to trust the count
+        assert_eq!(hold(1000, 1000, |now| counter.step(0.105, now)), None);
+        assert_eq!(counter.unit(), 0.005);
+    }
+
+    #[test]
+    fn warns_about_tiny_pieces() {
+        assert!(too_light(0.0008));
+        assert!(too_light(0.002));
+        assert!(!too_light(0.005));
+    }
+}
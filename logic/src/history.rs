@@ -0,0 +1,237 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! A record of every finished session, so you can see how closely a
+//! recipe got followed from one time to the next.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// anything earlier than 2001 must be seconds since boot instead
+const UNIX_FLOOR: u32 = 1_000_000_000;
+
+/// What a start time becomes once it's carried over a restart without
+/// the clock set: seconds since an earlier boot say nothing about how
+/// long ago that was. It still counts as started, just with no telling
+/// how long it took.
+pub const RESUMED: u32 = 1;
+
+/// A session's start time, restored after a restart. Unix times still
+/// mean the same, but seconds since the last boot become `RESUMED`.
+pub fn resumed(started: u32) -> u32 {
+    if started == 0 || started >= UNIX_FLOOR { started } else { RESUMED }
+}
+
+/// There's no battery-backed clock, so it only knows the time once
+/// it's been told over serial. Until then, times are seconds since
+/// boot.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Clock {
+    // Unix time when it was set, and when that was
+    set: Option<(u32, u64)>,
+    /// Minutes ahead of UTC, for showing times.
+    pub utc_offset: i32,
+}
+
+impl Clock {
+    pub fn set(&mut self, unix: u32, now_ms: u64) {
+        self.set = Some((unix, now_ms));
+    }
+
+    /// Whether `now` gives Unix time.
+    pub fn is_set(&self) -> bool {
+        self.set.is_some()
+    }
+
+    pub fn now(&self, now_ms: u64) -> u32 {
+        match self.set {
+            Some((unix, set_ms)) => unix + (now_ms.saturating_sub(set_ms) / 1000) as u32,
+            None => (now_ms / 1000) as u32,
+        }
+    }
+
+    /// A Unix time as a local "2024-03-09 14:05".
+    pub fn date_text(&self, unix: u32) -> String {
+        let local = unix as i64 + self.utc_offset as i64 * 60;
+        let (days, secs) = (local.div_euclid(86400), local.rem_euclid(86400));
+        // days to a civil date, from Howard Hinnant's algorithms
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        format!("{}-{:02}-{:02} {:02}:{:02}", year, month, day, secs / 3600, secs % 3600 / 60)
+    }
+}
+
+/// How one ingredient went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub name: String,
+    /// "g" for weighed ones, otherwise whatever it was measured in.
+    pub unit: String,
+    /// Both scaled, in `unit`s. Measured ones count as exactly on
+    /// target once they're done.
+    pub target: f32,
+    pub actual: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub recipe: String,
+    pub scale_factor: f32,
+    /// Both Unix time if `clock_set`, otherwise seconds since that
+    /// boot.
+    pub started: u32,
+    pub ended: u32,
+    pub clock_set: bool,
+    pub lines: Vec<Line>,
+}
+
+pub const CSV_HEADER: &str = "session,recipe,scale_factor,started,ended,clock_set,ingredient,unit,target,actual";
+
+// keeps commas and quotes in names from breaking up the row
+fn csv_field(val: &str) -> String {
+    if val.contains([',', '"', '\n']) {
+        format!("\"{}\"", val.replace('"', "\"\""))
+    } else {
+        val.into()
+    }
+}
+
+fn json_string(val: &str) -> String {
+    let mut out = String::from("\"");
+    for c in val.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Session {
+    /// How long it took, in seconds, if we can tell: not if it was
+    /// started before the clock was set, or before a restart.
+    pub fn seconds(&self) -> Option<u32> {
+        let same_clock = (self.started >= UNIX_FLOOR) == self.clock_set;
+        self.ended.checked_sub(self.started).filter(|_| self.started > RESUMED && same_clock)
+    }
+
+    /// The furthest any weighed ingredient landed from its target, as
+    /// a fraction of it.
+    pub fn worst_miss(&self) -> f32 {
+        self.lines.iter()
+            .filter(|line| line.unit == "g" && line.target > 0.)
+            .map(|line| (line.actual - line.target).abs() / line.target)
+            .fold(0., f32::max)
+    }
+
+    /// One row per ingredient, under `CSV_HEADER`. `session` tells
+    /// which rows go together.
+    pub fn csv_rows(&self, session: usize) -> impl Iterator<Item = String> + '_ {
+        self.lines.iter().map(move |line| {
+            format!(
+                "{},{},{},{},{},{},{},{},{:.2},{:.2}",
+                session, csv_field(&self.recipe), self.scale_factor, self.started, self.ended,
+                self.clock_set, csv_field(&line.name), line.unit, line.target, line.actual,
+            )
+        })
+    }
+
+    /// The whole session as one line of JSON.
+    pub fn json(&self, session: usize) -> String {
+        let lines: Vec<String> = self.lines.iter()
+            .map(|line| {
+                format!(
+                    "{{\"name\":{},\"unit\":{},\"target\":{:.2},\"actual\":{:.2}}}",
+                    json_string(&line.name), json_string(&line.unit), line.target, line.actual,
+                )
+            })
+            .collect();
+        format!(
+            "{{\"session\":{},\"recipe\":{},\"scale_factor\":{},\"started\":{},\"ended\":{},\"clock_set\":{},\"ingredients\":[{}]}}",
+            session, json_string(&self.recipe), self.scale_factor, self.started, self.ended, self.clock_set,
+            lines.join(","),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn session() -> Session {
+        Session {
+            recipe: "Bread, \"basic\"".into(),
+            scale_factor: 1.5,
+            started: 1_700_000_000,
+            ended: 1_700_000_600,
+            clock_set: true,
+            lines: vec![
+                Line { name: "flour".into(), unit: "g".into(), target: 500., actual: 510. },
+                Line { name: "water".into(), unit: "g".into(), target: 350., actual: 343. },
+                Line { name: "salt".into(), unit: "tsp".into(), target: 1.5, actual: 1.5 },
+            ],
+        }
+    }
+
+    #[test]
+    fn keeps_time() {
+        let mut clock = Clock::default();
+        assert_eq!(clock.now(5_500), 5);
+        clock.set(1_700_000_000, 10_000);
+        assert_eq!(clock.now(12_000), 1_700_000_002);
+        assert_eq!(clock.date_text(1_700_000_000), "2023-11-14 22:13");
+        clock.utc_offset = -5 * 60;
+        assert_eq!(clock.date_text(1_700_000_000), "2023-11-14 17:13");
+        assert_eq!(clock.date_text(951_782_400), "2000-02-28 19:00");
+    }
+
+    #[test]
+    fn measures_consistency() {
+        let session = session();
+        assert!((session.worst_miss() - 0.02).abs() < 1e-6);
+        assert_eq!(session.seconds(), Some(600));
+        let early = Session { started: 300, ..session };
+        assert_eq!(early.seconds(), None);
+    }
+
+    #[test]
+    fn times_sessions_without_a_clock_within_a_boot() {
+        let unset = Session { started: 300, ended: 900, clock_set: false, ..session() };
+        assert_eq!(unset.seconds(), Some(600));
+        // going by Unix time, nothing changes across a restart
+        assert_eq!(resumed(1_700_000_000), 1_700_000_000);
+        assert_eq!(resumed(0), 0);
+    }
+
+    #[test]
+    fn cant_time_sessions_carried_over_a_restart() {
+        // 300s after the last boot, and now 200s after this one
+        let resumed = Session { started: resumed(300), ended: 200, clock_set: false, ..session() };
+        assert_eq!(resumed.seconds(), None);
+        // even once the new boot's been going longer than the old one
+        let later = Session { ended: 900, ..resumed };
+        assert_eq!(later.seconds(), None);
+    }
+
+    #[test]
+    fn exports() {
+        let session = session();
+        let rows: Vec<String> = session.csv_rows(3).collect();
+        assert_eq!(rows[0], "3,\"Bread, \"\"basic\"\"\",1.5,1700000000,1700000600,true,flour,g,500.00,510.00");
+        assert_eq!(rows.len(), 3);
+        assert!(session.json(3).starts_with("{\"session\":3,\"recipe\":\"Bread, \\\"basic\\\"\",\"scale_factor\":1.5,"));
+        assert!(session.json(3).ends_with("{\"name\":\"salt\",\"unit\":\"tsp\",\"target\":1.50,\"actual\":1.50}]}"));
+    }
+}
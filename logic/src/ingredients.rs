@@ -0,0 +1,183 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! What we know about ingredients apart from any one recipe: what
+//! they're really called, how dense they are, what's in them, and what
+//! else would do instead.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const GLUTEN: u16 = 1 << 0;
+pub const EGGS: u16 = 1 << 1;
+pub const MILK: u16 = 1 << 2;
+pub const TREE_NUTS: u16 = 1 << 3;
+pub const PEANUTS: u16 = 1 << 4;
+pub const SOY: u16 = 1 << 5;
+pub const SESAME: u16 = 1 << 6;
+pub const FISH: u16 = 1 << 7;
+pub const SHELLFISH: u16 = 1 << 8;
+
+const ALLERGEN_NAMES: [(u16, &str); 9] = [
+    (GLUTEN, "gluten"),
+    (EGGS, "eggs"),
+    (MILK, "milk"),
+    (TREE_NUTS, "tree nuts"),
+    (PEANUTS, "peanuts"),
+    (SOY, "soy"),
+    (SESAME, "sesame"),
+    (FISH, "fish"),
+    (SHELLFISH, "shellfish"),
+];
+
+/// The names of the allergens in a set of flags.
+pub fn allergen_names(allergens: u16) -> impl Iterator<Item = &'static str> {
+    ALLERGEN_NAMES.into_iter().filter(move |&(flag, _)| allergens & flag != 0).map(|(_, name)| name)
+}
+
+// US measures, which is what the recipes are written in
+pub const ML_PER_TEASPOON: f32 = 4.929;
+pub const ML_PER_TABLESPOON: f32 = 14.787;
+pub const ML_PER_CUP: f32 = 236.59;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Nutrition {
+    pub kcal: f32,
+    // the rest in g
+    pub protein: f32,
+    pub fat: f32,
+    pub carbs: f32,
+}
+
+impl Nutrition {
+    /// Adds in `grams` of something with `per_100g` in every 100g.
+    pub fn add(&mut self, per_100g: &Nutrition, grams: f32) {
+        let portion = grams / 100.;
+        self.kcal += per_100g.kcal * portion;
+        self.protein += per_100g.protein * portion;
+        self.fat += per_100g.fat * portion;
+        self.carbs += per_100g.carbs * portion;
+    }
+}
+
+/// Something that does instead, `ratio` times as much by weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitute {
+    pub name: String,
+    pub ratio: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// What it's properly called, and what recipes refer to it by.
+    pub name: String,
+    /// Whatever else recipes might call it.
+    pub aliases: Vec<String>,
+    /// In g/ml, or 0 if it's not something that gets measured by volume.
+    pub density: f32,
+    pub allergens: u16,
+    pub per_100g: Nutrition,
+    pub substitutes: Vec<Substitute>,
+}
+
+impl Entry {
+    /// What a volume of it weighs, in g, if we know.
+    pub fn grams(&self, ml: f32) -> Option<f32> {
+        (self.density > 0.).then_some(ml * self.density)
+    }
+}
+
+// whether an ingredient's name is this, ignoring case and anything in
+// parentheses, like "water (80F)"
+fn same_name(ingredient: &str, name: &str) -> bool {
+    let ingredient = ingredient.split('(').next().unwrap_or_default().trim();
+    ingredient.eq_ignore_ascii_case(name.trim())
+}
+
+/// The entry an ingredient called `name` is, going by its name or any
+/// of its aliases.
+pub fn find<'a>(entries: &'a [Entry], name: &str) -> Option<&'a Entry> {
+    entries.iter().find(|entry| {
+        same_name(name, &entry.name) || entry.aliases.iter().any(|alias| same_name(name, alias))
+    })
+}
+
+/// The entry called exactly `name`, for ingredients already linked to
+/// one.
+pub fn get<'a>(entries: &'a [Entry], name: &str) -> Option<&'a Entry> {
+    entries.iter().find(|entry| entry.name == name)
+}
+
+/// The built-in entries with the user's own added, any of which are
+/// called the same as a built-in one taking its place.
+pub fn merge(defaults: Vec<Entry>, own: Vec<Entry>) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = defaults.into_iter()
+        .filter(|entry| get(&own, &entry.name).is_none())
+        .collect();
+    entries.extend(own);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn entry(name: &str, aliases: &[&str]) -> Entry {
+        Entry {
+            name: name.into(),
+            aliases: aliases.iter().map(|&alias| alias.into()).collect(),
+            density: 0.,
+            allergens: 0,
+            per_100g: Nutrition::default(),
+            substitutes: vec![],
+        }
+    }
+
+    #[test]
+    fn finds_by_name_or_alias() {
+        let entries = [entry("all-purpose flour", &["flour", "APF"]), entry("water", &[])];
+        assert_eq!(find(&entries, "Flour").unwrap().name, "all-purpose flour");
+        assert_eq!(find(&entries, "apf").unwrap().name, "all-purpose flour");
+        assert_eq!(find(&entries, "water (80F)").unwrap().name, "water");
+        assert!(find(&entries, "low-protein APF").is_none());
+        assert!(get(&entries, "flour").is_none());
+    }
+
+    #[test]
+    fn adds_up_nutrition() {
+        let butter = Nutrition { kcal: 717., protein: 0.9, fat: 81., carbs: 0.1 };
+        let mut total = Nutrition::default();
+        total.add(&butter, 50.);
+        total.add(&butter, 150.);
+        assert!((total.kcal - 1434.).abs() < 1e-3);
+        assert!((total.fat - 162.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn weighs_volumes() {
+        let mut sugar = entry("sugar", &[]);
+        assert_eq!(sugar.grams(ML_PER_CUP), None);
+        sugar.density = 0.85;
+        assert!((sugar.grams(ML_PER_CUP).unwrap() - 201.1).abs() < 0.1);
+    }
+
+    #[test]
+    fn names_allergens() {
+        assert!(allergen_names(GLUTEN | MILK).eq(["gluten", "milk"]));
+        assert_eq!(allergen_names(0).count(), 0);
+    }
+
+    #[test]
+    fn own_entries_win() {
+        let mut own_water = entry("water", &[]);
+        own_water.density = 0.99;
+        let merged = merge(
+            vec![entry("all-purpose flour", &["flour"]), entry("water", &[])],
+            vec![own_water, entry("rye flour", &[])],
+        );
+        let names: Vec<&str> = merged.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["all-purpose flour", "water", "rye flour"]);
+        assert_eq!(get(&merged, "water").unwrap().density, 0.99);
+    }
+}
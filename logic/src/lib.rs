@@ -0,0 +1,29 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! The parts of the firmware that don't need the hardware or the UI.
+//! They live here, away from esp-hal and Slint, so `cargo test` can
+//! run them on the host.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod buzzer;
+pub mod catalog;
+pub mod containers;
+pub mod counting;
+pub mod history;
+pub mod ingredients;
+pub mod pantry;
+pub mod portions;
+pub mod rebalance;
+pub mod serial;
+pub mod sessions;
+pub mod settle;
+pub mod store;
+pub mod stream;
+pub mod timers;
+pub mod tolerance;
+pub mod trend;
+pub mod wheel;
@@ -0,0 +1,104 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! How much of each ingredient is left in the pantry, going by what's
+//! been weighed in and what recipes have used.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stock {
+    /// The ingredient database entry it's stock of.
+    pub name: String,
+    /// How much is left, in kg.
+    pub amount: f32,
+    /// It's running low once there's this much or less left.
+    pub low: f32,
+}
+
+impl Stock {
+    pub fn is_low(&self) -> bool {
+        self.amount <= self.low
+    }
+}
+
+/// Keeps track of everything that's been restocked at least once, so
+/// only what anyone's bothered to weigh in gets counted.
+#[derive(Debug, Default)]
+pub struct Pantry {
+    pub stock: Vec<Stock>,
+}
+
+impl Pantry {
+    /// Takes what a recipe used out of stock: (entry, kg) for each
+    /// ingredient. Anything not being tracked is ignored. Returns
+    /// whether anything changed.
+    pub fn use_up<'a>(&mut self, used: impl Iterator<Item = (&'a str, f32)>) -> bool {
+        let mut changed = false;
+        for (name, amount) in used {
+            if let Some(stock) = self.stock.iter_mut().find(|stock| stock.name == name) {
+                stock.amount = (stock.amount - amount).max(0.);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Adds a bag's worth. The first time, it starts being tracked,
+    /// and counts as low once it's down to a quarter of that first bag.
+    pub fn restock(&mut self, name: &str, amount: f32) {
+        match self.stock.iter_mut().find(|stock| stock.name == name) {
+            Some(stock) => stock.amount += amount,
+            None => self.stock.push(Stock { name: name.into(), amount, low: amount / 4. }),
+        }
+    }
+
+    /// Stops keeping track of something.
+    pub fn forget(&mut self, name: &str) {
+        self.stock.retain(|stock| stock.name != name);
+    }
+
+    pub fn low(&self) -> impl Iterator<Item = &Stock> {
+        self.stock.iter().filter(|stock| stock.is_low())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restocks_and_uses_up() {
+        let mut pantry = Pantry::default();
+        pantry.restock("all-purpose flour", 2.);
+        assert_eq!(pantry.stock[0].low, 0.5);
+        assert!(pantry.use_up([("all-purpose flour", 0.5), ("salt", 0.01)].into_iter()));
+        assert_eq!(pantry.stock[0].amount, 1.5);
+        pantry.restock("all-purpose flour", 1.);
+        assert_eq!(pantry.stock[0].amount, 2.5);
+        // the threshold stays where it started
+        assert_eq!(pantry.stock[0].low, 0.5);
+    }
+
+    #[test]
+    fn ignores_what_isnt_tracked() {
+        let mut pantry = Pantry::default();
+        pantry.restock("salt", 1.);
+        assert!(!pantry.use_up([("sugar", 0.2)].into_iter()));
+        assert_eq!(pantry.stock.len(), 1);
+    }
+
+    #[test]
+    fn runs_low_and_out() {
+        let mut pantry = Pantry::default();
+        pantry.restock("butter", 0.5);
+        pantry.restock("sugar", 1.);
+        pantry.use_up([("butter", 0.4), ("sugar", 0.1)].into_iter());
+        assert!(pantry.low().map(|stock| stock.name.as_str()).eq(["butter"]));
+        pantry.use_up([("butter", 0.4)].into_iter());
+        assert_eq!(pantry.stock[0].amount, 0.);
+        pantry.forget("butter");
+        assert_eq!(pantry.low().count(), 0);
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Dividing a batch into equal portions, one at a time.
+
+/// How many portions of at least `portion` a batch makes. Whatever
+/// doesn't make a whole one gets shared out among the rest.
+pub fn count_for_weight(total: f32, portion: f32) -> u32 {
+    if portion <= 0. {
+        return 1;
+    }
+    // `as` truncates, and 1.2kg in 80g portions comes out a hair under
+    // 15 in floats, so anything that close to whole counts as it
+    ((total / portion + 1e-4) as u32).max(1)
+}
+
+/// What the next portion should weigh. Going by what's left rather than
+/// a fixed size means any earlier portion that came out heavy or light
+/// gets made up for by the rest.
+pub fn target(remaining: f32, pieces_left: u32) -> f32 {
+    if pieces_left == 0 {
+        return 0.;
+    }
+    remaining.max(0.) / pieces_left as f32
+}
+
+/// Once they're all cut, how much more each of the `pieces` gets to use
+/// up what's left over.
+pub fn leftover_share(remaining: f32, pieces_left: u32, pieces: u32) -> f32 {
+    if pieces_left > 0 || pieces == 0 || remaining <= 0. {
+        return 0.;
+    }
+    remaining / pieces as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_whole_portions() {
+        assert_eq!(count_for_weight(1.0, 0.08), 12);
+        assert_eq!(count_for_weight(0.96, 0.08), 12);
+        assert_eq!(count_for_weight(0.95, 0.08), 11);
+        assert_eq!(count_for_weight(1.2, 0.08), 15);
+        // never nothing
+        assert_eq!(count_for_weight(0.05, 0.08), 1);
+        assert_eq!(count_for_weight(1.0, 0.), 1);
+    }
+
+    #[test]
+    fn evens_out_as_it_goes() {
+        assert!((target(0.9, 3) - 0.3).abs() < 1e-6);
+        // a heavy one means lighter ones after
+        assert!((target(0.9 - 0.33, 2) - 0.285).abs() < 1e-6);
+        // the last one's whatever's left
+        assert!((target(0.29, 1) - 0.29).abs() < 1e-6);
+        assert_eq!(target(0.01, 0), 0.);
+        assert_eq!(target(-0.01, 1), 0.);
+    }
+
+    #[test]
+    fn shares_out_the_leftovers() {
+        assert!((leftover_share(0.04, 0, 4) - 0.01).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nothing_to_share_partway_or_when_short() {
+        assert_eq!(leftover_share(0.8, 3, 4), 0.);
+        assert_eq!(leftover_share(-0.1, 0, 4), 0.);
+        assert_eq!(leftover_share(0.1, 0, 0), 0.);
+    }
+}
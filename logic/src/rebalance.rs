@@ -0,0 +1,95 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Rescaling the rest of a recipe around an ingredient that went over.
+//!
+//! Targets here are what the recipe currently asks for, in kg, after
+//! any scaling. Tolerances are as in [`crate::tolerance`].
+
+/// Going over by less than the scale can really tell apart isn't worth
+/// rescaling everything else for, whatever the tolerance.
+pub const RESOLUTION: f32 = 0.001;
+
+/// How many times its target an ingredient came out to, if that's far
+/// enough over to be worth rescaling the recipe for.
+pub fn overage(target: f32, actual: f32, tolerance: f32) -> Option<f32> {
+    let over = actual - target;
+    (target > 0. && over > RESOLUTION && over > tolerance).then(|| actual / target)
+}
+
+/// The scale factor that makes an over-poured ingredient's target
+/// what was actually poured, so everything else can follow it.
+pub fn rebalanced_factor(factor: f32, target: f32, actual: f32, tolerance: f32) -> f32 {
+    match overage(target, actual, tolerance) {
+        Some(ratio) => factor * ratio,
+        None => factor,
+    }
+}
+
+/// How much more of an ingredient that's already in it takes to reach
+/// its target, ignoring anything too small to weigh.
+pub fn top_up(target: f32, actual: f32) -> f32 {
+    let short = target - actual;
+    if short > RESOLUTION { short } else { 0. }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the default for most of these targets
+    const TOLERANCE: f32 = 0.002;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn small_overage_is_ignored() {
+        assert_eq!(overage(0.128, 0.129, TOLERANCE), None);
+        assert_eq!(overage(0.128, 0.128, TOLERANCE), None);
+        assert_eq!(overage(0.128, 0.100, TOLERANCE), None);
+        // a tighter tolerance than the scale's resolution
+        assert_eq!(overage(0.020, 0.0209, 0.0005), None);
+    }
+
+    #[test]
+    fn big_overage_gives_ratio() {
+        let ratio = overage(0.128, 0.140, TOLERANCE).unwrap();
+        assert!(close(ratio, 0.140 / 0.128));
+    }
+
+    #[test]
+    fn nothing_to_rescale_against_without_a_target() {
+        assert_eq!(overage(0., 0.010, TOLERANCE), None);
+    }
+
+    #[test]
+    fn rebalancing_scales_the_factor() {
+        assert!(close(rebalanced_factor(1., 0.128, 0.140, TOLERANCE), 0.140 / 0.128));
+        assert!(close(rebalanced_factor(0.5, 0.064, 0.070, TOLERANCE), 0.5 * 0.070 / 0.064));
+        assert!(close(rebalanced_factor(2., 0.256, 0.257, TOLERANCE), 2.));
+    }
+
+    #[test]
+    fn rebalanced_target_matches_what_was_poured() {
+        let amount = 0.128;
+        let factor = rebalanced_factor(1., amount, 0.140, TOLERANCE);
+        assert!(close(amount * factor, 0.140));
+    }
+
+    #[test]
+    fn done_ingredients_get_topped_up() {
+        // 235g of water went in before 140g of flour on a 128g target
+        let factor = rebalanced_factor(1., 0.128, 0.140, TOLERANCE);
+        let top_up = top_up(0.235 * factor, 0.235);
+        assert!(close(top_up, 0.235 * 0.140 / 0.128 - 0.235));
+    }
+
+    #[test]
+    fn no_top_up_when_already_there() {
+        assert_eq!(top_up(0.100, 0.100), 0.);
+        assert_eq!(top_up(0.100, 0.1005), 0.);
+        assert_eq!(top_up(0.100, 0.120), 0.);
+    }
+}
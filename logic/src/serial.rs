@@ -0,0 +1,206 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Line-based commands over the USB serial port. Each command is one
+//! line of space-separated words, e.g. `stream csv 100`.
+
+use alloc::string::String;
+
+use crate::stream::StreamFormat;
+
+/// Anything longer is thrown away.
+pub const LINE_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Start streaming readings in the given format, one every
+    /// `period_ms` milliseconds.
+    Stream { format: StreamFormat, period_ms: u64 },
+    StreamOff,
+    /// Feed a raw ADC value to the scale in place of the load cell.
+    Fake(i32),
+    FakeOff,
+    /// List the saved containers.
+    Containers,
+    /// Save a container that weighs `grams` empty.
+    AddContainer { name: String, grams: f32 },
+    RemoveContainer { name: String },
+    /// List what's in the pantry, or just what's running low.
+    Pantry { low_only: bool },
+    /// Print every logged session.
+    History(StreamFormat),
+    /// Set the clock to a Unix time, and how many minutes ahead of UTC
+    /// to show times.
+    Clock { unix: u32, utc_offset: i32 },
+}
+
+// everything left on the line, as one name
+fn rest<'a>(words: impl Iterator<Item = &'a str>) -> Result<String, &'static str> {
+    let mut name = String::new();
+    for word in words {
+        if !name.is_empty() {
+            name.push(' ');
+        }
+        name.push_str(word);
+    }
+    if name.is_empty() {
+        return Err("expected a name");
+    }
+    Ok(name)
+}
+
+pub fn parse(line: &str) -> Result<Command, &'static str> {
+    let mut words = line.split_ascii_whitespace();
+    match words.next() {
+        Some("stream") => {
+            let format = match words.next() {
+                Some("off") => return Ok(Command::StreamOff),
+                Some("csv") => StreamFormat::Csv,
+                Some("json") => StreamFormat::Json,
+                _ => return Err("expected csv, json or off"),
+            };
+            let period_ms = match words.next() {
+                Some(period) => period.parse().map_err(|_| "bad period")?,
+                None => 100,
+            };
+            if period_ms == 0 {
+                return Err("period should be more than 0");
+            }
+            Ok(Command::Stream { format, period_ms })
+        },
+        Some("fake") =>
+            match words.next() {
+                Some("off") => Ok(Command::FakeOff),
+                Some(val) => val.parse().map(Command::Fake).map_err(|_| "bad raw value"),
+                None => Err("expected raw value or off"),
+            },
+        Some("containers") =>
+            Ok(Command::Containers),
+        Some("container") =>
+            match words.next() {
+                Some("add") => {
+                    let grams: f32 = words.next()
+                        .ok_or("expected grams")?
+                        .parse()
+                        .map_err(|_| "bad grams")?;
+                    // which also keeps out nan and inf
+                    if !(grams > 0. && grams.is_finite()) {
+                        return Err("grams should be more than 0");
+                    }
+                    Ok(Command::AddContainer { name: rest(words)?, grams })
+                },
+                Some("remove") => Ok(Command::RemoveContainer { name: rest(words)? }),
+                _ => Err("expected add or remove"),
+            },
+        Some("pantry") =>
+            match words.next() {
+                None => Ok(Command::Pantry { low_only: false }),
+                Some("low") => Ok(Command::Pantry { low_only: true }),
+                _ => Err("expected low or nothing"),
+            },
+        Some("history") =>
+            match words.next() {
+                Some("csv") => Ok(Command::History(StreamFormat::Csv)),
+                Some("json") => Ok(Command::History(StreamFormat::Json)),
+                _ => Err("expected csv or json"),
+            },
+        Some("clock") => {
+            let unix = words.next()
+                .ok_or("expected unix time")?
+                .parse()
+                .map_err(|_| "bad unix time")?;
+            let utc_offset = match words.next() {
+                Some(offset) => offset.parse().map_err(|_| "bad utc offset")?,
+                None => 0,
+            };
+            Ok(Command::Clock { unix, utc_offset })
+        },
+        _ =>
+            Err("unknown command"),
+    }
+}
+
+/// Accumulates bytes from the serial port until a full line arrives.
+#[derive(Default)]
+pub struct LineReader {
+    line: String,
+    overflowed: bool,
+}
+
+impl LineReader {
+    /// Feeds in one byte. Returns the parsed command when it
+    /// completes a line; empty lines are ignored.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Command, &'static str>> {
+        match byte {
+            b'\r' | b'\n' => {
+                let result = if self.overflowed {
+                    Some(Err("line too long"))
+                } else if self.line.trim().is_empty() {
+                    None
+                } else {
+                    Some(parse(&self.line))
+                };
+                self.line.clear();
+                self.overflowed = false;
+                result
+            },
+            _ => {
+                if self.line.len() < LINE_LEN {
+                    self.line.push(byte as char);
+                } else {
+                    self.overflowed = true;
+                }
+                None
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(reader: &mut LineReader, text: &str) -> Option<Result<Command, &'static str>> {
+        text.bytes().filter_map(|byte| reader.push(byte)).last()
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("stream json 250"), Ok(Command::Stream { format: StreamFormat::Json, period_ms: 250 }));
+        assert_eq!(parse("stream csv"), Ok(Command::Stream { format: StreamFormat::Csv, period_ms: 100 }));
+        assert_eq!(parse("stream off"), Ok(Command::StreamOff));
+        assert_eq!(parse("fake -42"), Ok(Command::Fake(-42)));
+        assert_eq!(
+            parse("container add 350  big   bowl"),
+            Ok(Command::AddContainer { name: "big bowl".into(), grams: 350. }),
+        );
+        assert_eq!(parse("container remove big bowl"), Ok(Command::RemoveContainer { name: "big bowl".into() }));
+        assert_eq!(parse("pantry low"), Ok(Command::Pantry { low_only: true }));
+        assert_eq!(parse("history csv"), Ok(Command::History(StreamFormat::Csv)));
+        assert_eq!(parse("clock 1700000000 -300"), Ok(Command::Clock { unix: 1_700_000_000, utc_offset: -300 }));
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        assert_eq!(parse("stream xml"), Err("expected csv, json or off"));
+        assert_eq!(parse("stream csv 0"), Err("period should be more than 0"));
+        assert_eq!(parse("fake lots"), Err("bad raw value"));
+        assert_eq!(parse("container add 350"), Err("expected a name"));
+        for grams in ["nan", "inf", "-5", "0"] {
+            assert_eq!(parse(&alloc::format!("container add {} bowl", grams)), Err("grams should be more than 0"));
+        }
+        assert_eq!(parse("clock soon"), Err("bad unix time"));
+        assert_eq!(parse("dance"), Err("unknown command"));
+    }
+
+    #[test]
+    fn reads_lines() {
+        let mut reader = LineReader::default();
+        assert_eq!(feed(&mut reader, "\r\n   \n"), None);
+        assert_eq!(feed(&mut reader, "fake off\r\n"), Some(Ok(Command::FakeOff)));
+        let long = "x".repeat(LINE_LEN + 1) + "\n";
+        assert_eq!(feed(&mut reader, &long), Some(Err("line too long")));
+        // and it's back to normal after
+        assert_eq!(feed(&mut reader, "stream off\n"), Some(Ok(Command::StreamOff)));
+    }
+}
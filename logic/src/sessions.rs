@@ -0,0 +1,97 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! How a recipe that's partway through gets journaled, so it survives
+//! the battery running out.
+
+use alloc::vec::Vec;
+
+use crate::store::{Decoder, Encoder};
+
+const VERSION: u32 = 1;
+
+/// One recipe's progress, as it's kept in flash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub scale_factor: f32,
+    pub current_ingredient: u32,
+    /// Whether each ingredient's done, and how much of it went in.
+    pub ingredients: Vec<(bool, f32)>,
+    pub cumulative: bool,
+    pub bowl_weight: f32,
+    pub started: u32,
+    /// The last step's been done, and the pantry and history have had
+    /// it, so doing it again doesn't count it twice.
+    pub finished: bool,
+}
+
+/// Each record is one recipe's progress, under its name, so it still
+/// finds the right recipe if others are added before it.
+pub fn encode(name: &str, progress: &Progress) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.u32(VERSION);
+    encoder.str(name);
+    encoder.f32(progress.scale_factor);
+    encoder.u32(progress.current_ingredient);
+    encoder.u32(progress.ingredients.len() as u32);
+    for &(done, amount) in &progress.ingredients {
+        encoder.u8(done as u8);
+        encoder.f32(amount);
+    }
+    encoder.u8(progress.cumulative as u8);
+    encoder.f32(progress.bowl_weight);
+    encoder.u32(progress.started);
+    encoder.u8(progress.finished as u8);
+    encoder.finish()
+}
+
+pub fn decode(data: &[u8]) -> Option<(&str, Progress)> {
+    let mut decoder = Decoder::new(data);
+    if decoder.u32()? != VERSION {
+        return None;
+    }
+    let name = decoder.str()?;
+    let scale_factor = decoder.f32()?;
+    let current_ingredient = decoder.u32()?;
+    let count = decoder.u32()?;
+    let ingredients = (0..count)
+        .map(|_| Some((decoder.u8()? != 0, decoder.f32()?)))
+        .collect::<Option<Vec<_>>>()?;
+    let cumulative = decoder.u8()? != 0;
+    let bowl_weight = decoder.f32()?;
+    let started = decoder.u32()?;
+    let finished = decoder.u8()? != 0;
+    Some((name, Progress { scale_factor, current_ingredient, ingredients, cumulative, bowl_weight, started, finished }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn progress() -> Progress {
+        Progress {
+            scale_factor: 1.5,
+            current_ingredient: 2,
+            ingredients: vec![(true, 0.5), (true, 0.012), (false, 0.)],
+            cumulative: true,
+            bowl_weight: 0.512,
+            started: 1_700_000_000,
+            finished: false,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let progress = progress();
+        assert_eq!(decode(&encode("Bread", &progress)), Some(("Bread", progress)));
+    }
+
+    #[test]
+    fn stays_finished_after_a_restart() {
+        // so finishing it again once it's back doesn't use up the
+        // pantry or log it a second time
+        let progress = Progress { finished: true, ..progress() };
+        assert!(decode(&encode("Bread", &progress)).unwrap().1.finished);
+    }
+}
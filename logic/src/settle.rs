@@ -0,0 +1,83 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Telling when the weight on the scale has stopped moving.
+
+/// How much the weight can wander and still count as settled.
+pub const STEADY: f32 = 0.001;
+/// How long it has to stay steady for.
+pub const SETTLE_MS: u64 = 500;
+
+/// Watches the weight for it settling, and says where, once each time
+/// it does.
+#[derive(Default)]
+pub struct Settle {
+    // where it's been since when, while it stays within STEADY
+    anchor: f32,
+    since_ms: u64,
+    // already said so for this settling
+    reported: bool,
+}
+
+impl Settle {
+    /// Starts off as if it had already settled at `weight`, so only a
+    /// change from there gets reported.
+    pub fn at(weight: f32) -> Settle {
+        Settle { anchor: weight, since_ms: 0, reported: true }
+    }
+
+    /// Returns where the weight's settled, the first time it's stayed
+    /// there long enough.
+    pub fn step(&mut self, weight: f32, now_ms: u64) -> Option<f32> {
+        if (weight - self.anchor).abs() > STEADY {
+            self.anchor = weight;
+            self.since_ms = now_ms;
+            self.reported = false;
+            return None;
+        }
+        if self.reported || now_ms.saturating_sub(self.since_ms) < SETTLE_MS {
+            return None;
+        }
+        self.reported = true;
+        Some(self.anchor)
+    }
+}
+
+/// Steps something every 100ms for a while, returning the last thing
+/// it said.
+#[cfg(test)]
+pub(crate) fn hold<T>(from_ms: u64, for_ms: u64, step: impl FnMut(u64) -> Option<T>) -> Option<T> {
+    (from_ms..from_ms + for_ms).step_by(100).filter_map(step).last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_it_to_settle() {
+        let mut settle = Settle::default();
+        assert_eq!(settle.step(0.35, 1000), None);
+        assert_eq!(settle.step(0.3505, 1200), None);
+        assert_eq!(settle.step(0.35, 1400), None);
+        assert_eq!(settle.step(0.35, 1500), Some(0.35));
+        // only the once
+        assert_eq!(settle.step(0.35, 1600), None);
+    }
+
+    #[test]
+    fn wobbling_starts_over() {
+        let mut settle = Settle::default();
+        assert_eq!(settle.step(0.35, 1000), None);
+        assert_eq!(settle.step(0.36, 1400), None);
+        assert_eq!(settle.step(0.36, 1800), None);
+        assert_eq!(settle.step(0.36, 1900), Some(0.36));
+    }
+
+    #[test]
+    fn starting_settled_waits_for_a_change() {
+        let mut settle = Settle::at(0.05);
+        assert_eq!(hold(0, 1000, |now| settle.step(0.05, now)), None);
+        assert_eq!(hold(1000, 1000, |now| settle.step(0.07, now)), Some(0.07));
+    }
+}
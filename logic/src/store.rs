@@ -0,0 +1,731 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Persistent storage in regions of flash, plus a tiny binary encoding
+//! for whatever we put there.
+
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use embedded_storage::nor_flash::NorFlash;
+use embedded_storage::Storage;
+
+/// A contiguous, sector-aligned area of flash.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub offset: u32,
+    pub size: u32,
+}
+
+const MAGIC: u32 = 0x4853_0002;
+// the magic, sequence number, length and checksum
+const HEADER_LEN: usize = 16;
+
+/// FNV-1a, to catch writes that got cut off.
+pub fn checksum(data: &[u8]) -> u32 {
+    data.iter().fold(0x811c_9dc5, |hash, &byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Keeps a single blob, in two copies that take turns getting
+/// overwritten, so losing power partway through a save leaves the last
+/// one whole. The two regions shouldn't share any sectors.
+pub struct Blob<F> {
+    flash: Rc<RefCell<F>>,
+    regions: [Region; 2],
+}
+
+impl<F: Storage> Blob<F> {
+    pub fn new(flash: Rc<RefCell<F>>, regions: [Region; 2]) -> Blob<F> {
+        Blob { flash, regions }
+    }
+
+    // a copy, and its sequence number, if it's all there
+    fn read(&self, region: Region) -> Option<(u32, Vec<u8>)> {
+        let mut flash = self.flash.borrow_mut();
+        let mut header = [0; HEADER_LEN];
+        flash.read(region.offset, &mut header).ok()?;
+        let mut header = Decoder::new(&header);
+        if header.u32()? != MAGIC {
+            return None;
+        }
+        let seq = header.u32()?;
+        let len = header.u32()? as usize;
+        let sum = header.u32()?;
+        if len > region.size as usize - HEADER_LEN {
+            return None;
+        }
+        let mut data = vec![0; len];
+        flash.read(region.offset + HEADER_LEN as u32, &mut data).ok()?;
+        (checksum(&data) == sum).then_some((seq, data))
+    }
+
+    // the newer of the copies that are all there, and which one it is
+    fn latest(&self) -> Option<(usize, u32, Vec<u8>)> {
+        (0..2)
+            .filter_map(|copy| self.read(self.regions[copy]).map(|(seq, data)| (copy, seq, data)))
+            .max_by_key(|&(_, seq, _)| seq)
+    }
+
+    /// Returns the saved blob, or `None` if nothing valid has been
+    /// saved yet.
+    pub fn load(&self) -> Option<Vec<u8>> {
+        self.latest().map(|(_, _, data)| data)
+    }
+
+    pub fn save(&self, data: &[u8]) -> Result<(), &'static str> {
+        let (copy, seq) = match self.latest() {
+            Some((copy, seq, _)) => (1 - copy, seq + 1),
+            None => (0, 1),
+        };
+        let region = self.regions[copy];
+        if data.len() > region.size as usize - HEADER_LEN {
+            return Err("too big for its region");
+        }
+        let mut encoder = Encoder::default();
+        encoder.u32(MAGIC);
+        encoder.u32(seq);
+        encoder.u32(data.len() as u32);
+        encoder.u32(checksum(data));
+        encoder.bytes(data);
+        self.flash.borrow_mut()
+            .write(region.offset, &encoder.finish())
+            .map_err(|_| "flash write failed")
+    }
+}
+
+const JOURNAL_MAGIC: u32 = 0x4853_4a01;
+const LOG_MAGIC: u32 = 0x4853_4c01;
+// a sector starts with its sequence number and the magic, and each
+// record with its length and checksum
+const SECTOR_HEADER_LEN: u32 = 8;
+const RECORD_HEADER_LEN: u32 = 8;
+
+// a region split into erasable sectors full of records, written one
+// sector at a time, for `Journal` and `Log`
+struct Sectors<F> {
+    flash: Rc<RefCell<F>>,
+    region: Region,
+    // tells ours apart from whatever else was in the region
+    magic: u32,
+    // the one being written to, or None until the first gets started
+    sector: Option<u32>,
+    seq: u32,
+    // where the next record goes, within the sector
+    end: u32,
+}
+
+impl<F: NorFlash> Sectors<F> {
+    // picks up from the latest started sector, returning its records in
+    // the order they were written
+    fn open(flash: Rc<RefCell<F>>, region: Region, magic: u32) -> (Sectors<F>, Vec<Vec<u8>>) {
+        let mut sectors = Sectors { flash, region, magic, sector: None, seq: 0, end: 0 };
+        let records = match latest(&sectors.seqs()) {
+            Some((sector, seq)) => {
+                let (records, end) = sectors.scan(sector);
+                sectors.sector = Some(sector);
+                sectors.seq = seq;
+                sectors.end = end;
+                records
+            },
+            None => Vec::new(),
+        };
+        (sectors, records)
+    }
+
+    fn count(&self) -> u32 {
+        self.region.size / F::ERASE_SIZE as u32
+    }
+
+    fn offset(&self, sector: u32) -> u32 {
+        self.region.offset + sector * F::ERASE_SIZE as u32
+    }
+
+    // what a record takes up, header and all, so the next one stays
+    // aligned
+    fn padded_len(len: usize) -> u32 {
+        let align = F::WRITE_SIZE.max(F::READ_SIZE);
+        (RECORD_HEADER_LEN as usize + len.div_ceil(align) * align) as u32
+    }
+
+    fn read_u32s(&self, offset: u32) -> Option<(u32, u32)> {
+        let mut header = [0; 8];
+        self.flash.borrow_mut().read(offset, &mut header).ok()?;
+        let mut header = Decoder::new(&header);
+        Some((header.u32()?, header.u32()?))
+    }
+
+    // the sequence numbers of the sectors that have been started, by
+    // sector
+    fn seqs(&self) -> Vec<Option<u32>> {
+        (0..self.count())
+            .map(|sector| match self.read_u32s(self.offset(sector)) {
+                Some((seq, found)) if found == self.magic => Some(seq),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // reads the records in a sector, and where the next one would go
+    fn scan(&self, sector: u32) -> (Vec<Vec<u8>>, u32) {
+        let base = self.offset(sector);
+        let sector_size = F::ERASE_SIZE as u32;
+        let mut records = Vec::new();
+        let mut offset = SECTOR_HEADER_LEN;
+        while offset + RECORD_HEADER_LEN <= sector_size {
+            let Some((len, sum)) = self.read_u32s(base + offset) else {
+                // can't tell where it ends, so don't add to it
+                offset = sector_size;
+                break;
+            };
+            if len == u32::MAX {
+                // still erased, so this is the end
+                break;
+            }
+            if len > sector_size - offset - RECORD_HEADER_LEN {
+                // a torn length, which could be anything
+                offset = sector_size;
+                break;
+            }
+            let padded = Self::padded_len(len as usize);
+            if offset + padded > sector_size {
+                offset = sector_size;
+                break;
+            }
+            let mut data = vec![0; padded as usize - RECORD_HEADER_LEN as usize];
+            let read = self.flash.borrow_mut().read(base + offset + RECORD_HEADER_LEN, &mut data);
+            data.truncate(len as usize);
+            if read.is_err() || checksum(&data) != sum {
+                // a write that got cut off
+                offset = sector_size;
+                break;
+            }
+            records.push(data);
+            offset += padded;
+        }
+        (records, offset)
+    }
+
+    fn write_record(&self, sector: u32, offset: u32, data: &[u8]) -> Result<u32, &'static str> {
+        let padded = Self::padded_len(data.len());
+        if offset + padded > F::ERASE_SIZE as u32 {
+            return Err("too big for a sector");
+        }
+        let mut encoder = Encoder::default();
+        encoder.u32(data.len() as u32);
+        encoder.u32(checksum(data));
+        encoder.bytes(data);
+        let mut buf = encoder.finish();
+        buf.resize(padded as usize, 0xff);
+        self.flash.borrow_mut()
+            .write(self.offset(sector) + offset, &buf)
+            .map_err(|_| "flash write failed")?;
+        Ok(offset + padded)
+    }
+
+    // adds a record to the current sector, or returns false if there's
+    // no room left in it
+    fn append(&mut self, data: &[u8]) -> Result<bool, &'static str> {
+        match self.sector {
+            Some(sector) if self.end + Self::padded_len(data.len()) <= F::ERASE_SIZE as u32 => {
+                self.end = self.write_record(sector, self.end, data)?;
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+
+    // erases the next sector and starts it with these records. It only
+    // gets its header once they're all there, so losing power partway
+    // leaves the old sector as the latest.
+    fn start_next(&mut self, records: &[Vec<u8>]) -> Result<(), &'static str> {
+        // check before erasing anything, or every append after this
+        // would erase the sector again only to fail the same way
+        let len = records.iter().map(|record| Self::padded_len(record.len())).sum::<u32>();
+        if SECTOR_HEADER_LEN + len > F::ERASE_SIZE as u32 {
+            return Err("too much to keep in a sector");
+        }
+        let sector = self.sector.map_or(0, |sector| (sector + 1) % self.count());
+        let offset = self.offset(sector);
+        self.flash.borrow_mut()
+            .erase(offset, offset + F::ERASE_SIZE as u32)
+            .map_err(|_| "flash erase failed")?;
+        let mut end = SECTOR_HEADER_LEN;
+        for record in records {
+            end = self.write_record(sector, end, record)?;
+        }
+        let seq = self.seq.wrapping_add(1);
+        let mut header = Encoder::default();
+        header.u32(seq);
+        header.u32(self.magic);
+        self.flash.borrow_mut()
+            .write(offset, &header.finish())
+            .map_err(|_| "flash write failed")?;
+        self.sector = Some(sector);
+        self.seq = seq;
+        self.end = end;
+        Ok(())
+    }
+}
+
+// the newest of the started sectors, and its sequence number
+fn latest(seqs: &[Option<u32>]) -> Option<(u32, u32)> {
+    seqs.iter()
+        .enumerate()
+        .filter_map(|(sector, seq)| Some((sector as u32, (*seq)?)))
+        .max_by_key(|&(_, seq)| seq)
+}
+
+/// Keeps a log of records in a region, for things that change too
+/// often to rewrite a whole `Blob` every time.
+///
+/// Records are appended to one sector until it fills up. Then the next
+/// sector is erased and started with a snapshot of whatever's still
+/// live, so each change costs one small write, and each sector gets
+/// erased once per trip around the region.
+pub struct Journal<F> {
+    sectors: Sectors<F>,
+}
+
+impl<F: NorFlash> Journal<F> {
+    /// Opens the journal, returning the latest sector's records in the
+    /// order they were written.
+    pub fn open(flash: Rc<RefCell<F>>, region: Region) -> (Journal<F>, Vec<Vec<u8>>) {
+        let (sectors, records) = Sectors::open(flash, region, JOURNAL_MAGIC);
+        (Journal { sectors }, records)
+    }
+
+    /// Adds a record. If the sector's full, the next one gets started
+    /// with `snapshot` instead, which should give back everything that
+    /// still matters, this record included. That has to fit in a
+    /// sector.
+    pub fn append(&mut self, data: &[u8], snapshot: impl FnOnce() -> Vec<Vec<u8>>) -> Result<(), &'static str> {
+        if self.sectors.append(data)? {
+            return Ok(());
+        }
+        self.sectors.start_next(&snapshot())
+    }
+}
+
+/// Keeps records in a region for good, or at least until it's full,
+/// when the oldest sector's worth get erased to make room.
+pub struct Log<F> {
+    sectors: Sectors<F>,
+}
+
+impl<F: NorFlash> Log<F> {
+    pub fn open(flash: Rc<RefCell<F>>, region: Region) -> Log<F> {
+        Log { sectors: Sectors::open(flash, region, LOG_MAGIC).0 }
+    }
+
+    /// Everything still in the log, oldest first. It's read from flash
+    /// a sector at a time as it goes, so the whole log never has to fit
+    /// in memory.
+    pub fn records(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        let mut started: Vec<(u32, u32)> = self.sectors.seqs()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(sector, seq)| Some((seq?, sector as u32)))
+            .collect();
+        started.sort_unstable();
+        started.into_iter().flat_map(|(_, sector)| self.sectors.scan(sector).0)
+    }
+
+    pub fn append(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        if self.sectors.append(data)? {
+            return Ok(());
+        }
+        // on to the oldest sector
+        self.sectors.start_next(&[data.to_vec()])
+    }
+}
+
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub fn u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, val: f32) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, val: &[u8]) {
+        self.buf.extend_from_slice(val);
+    }
+
+    /// Strings longer than 255 bytes get cut short.
+    pub fn str(&mut self, val: &str) {
+        let mut len = val.len().min(u8::MAX as usize);
+        while !val.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.u8(len as u8);
+        self.bytes(&val.as_bytes()[..len]);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buf: &'a [u8]) -> Decoder<'a> {
+        Decoder { buf }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.buf.len() {
+            return None;
+        }
+        let (val, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Some(val)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    pub fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    pub fn str(&mut self) -> Option<&'a str> {
+        let len = self.u8()? as usize;
+        core::str::from_utf8(self.bytes(len)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+    use embedded_storage::ReadStorage;
+
+    /// Flash in RAM, which can be told to lose power partway through a
+    /// write.
+    struct RamFlash {
+        data: Vec<u8>,
+        // how many more bytes get written before the power goes
+        power_left: Option<usize>,
+        erases: usize,
+    }
+
+    impl RamFlash {
+        fn new(size: usize) -> Rc<RefCell<RamFlash>> {
+            Rc::new(RefCell::new(RamFlash { data: vec![0xff; size], power_left: None, erases: 0 }))
+        }
+
+        fn store(&mut self, offset: u32, bytes: &[u8]) -> Result<(), ()> {
+            let start = offset as usize;
+            let len = self.power_left.map_or(bytes.len(), |left| left.min(bytes.len()));
+            self.data.get_mut(start..start + len).ok_or(())?.copy_from_slice(&bytes[..len]);
+            if let Some(left) = self.power_left.as_mut() {
+                *left -= len;
+                if len < bytes.len() {
+                    return Err(());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl ReadStorage for RamFlash {
+        type Error = ();
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), ()> {
+            let start = offset as usize;
+            bytes.copy_from_slice(self.data.get(start..start + bytes.len()).ok_or(())?);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl Storage for RamFlash {
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), ()> {
+            self.store(offset, bytes)
+        }
+    }
+
+    impl ErrorType for RamFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), NorFlashErrorKind> {
+            ReadStorage::read(self, offset, bytes).map_err(|_| NorFlashErrorKind::OutOfBounds)
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 0x100;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), NorFlashErrorKind> {
+            self.data.get_mut(from as usize..to as usize).ok_or(NorFlashErrorKind::OutOfBounds)?.fill(0xff);
+            self.erases += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), NorFlashErrorKind> {
+            self.store(offset, bytes).map_err(|_| NorFlashErrorKind::Other)
+        }
+    }
+
+    const COPIES: [Region; 2] = [Region { offset: 0, size: 0x100 }, Region { offset: 0x100, size: 0x100 }];
+    // three sectors, with room for twelve ten-byte records in each
+    const SECTORS: Region = Region { offset: 0, size: 0x300 };
+
+    #[test]
+    fn round_trips() {
+        let mut encoder = Encoder::default();
+        encoder.u8(7);
+        encoder.u32(0xdead_beef);
+        encoder.f32(-1.5);
+        encoder.str("crème");
+        encoder.bytes(&[1, 2]);
+        let data = encoder.finish();
+        let mut decoder = Decoder::new(&data);
+        assert_eq!(decoder.u8(), Some(7));
+        assert_eq!(decoder.u32(), Some(0xdead_beef));
+        assert_eq!(decoder.f32(), Some(-1.5));
+        assert_eq!(decoder.str(), Some("crème"));
+        assert_eq!(decoder.bytes(2), Some(&[1, 2][..]));
+        // and then it's run out
+        assert_eq!(decoder.u8(), None);
+    }
+
+    #[test]
+    fn cuts_long_strings_between_chars() {
+        let long: String = "é".repeat(200);
+        let mut encoder = Encoder::default();
+        encoder.str(&long);
+        encoder.u8(9);
+        let data = encoder.finish();
+        let mut decoder = Decoder::new(&data);
+        assert_eq!(decoder.str(), Some(&long[..254]));
+        assert_eq!(decoder.u8(), Some(9));
+    }
+
+    #[test]
+    fn rejects_short_and_garbled_input() {
+        assert_eq!(Decoder::new(&[1, 2, 3]).u32(), None);
+        assert_eq!(Decoder::new(&[4, b'a']).str(), None);
+        assert_eq!(Decoder::new(&[2, 0xff, 0xfe]).str(), None);
+    }
+
+    #[test]
+    fn alternates_copies() {
+        let flash = RamFlash::new(0x200);
+        let blob = Blob::new(flash.clone(), COPIES);
+        assert_eq!(blob.load(), None);
+        for n in 0..5u8 {
+            blob.save(&[n; 10]).unwrap();
+            assert_eq!(blob.load(), Some(vec![n; 10]));
+        }
+        // the last two are both still there
+        let data = &flash.borrow().data;
+        assert_eq!(data[HEADER_LEN], 4);
+        assert_eq!(data[0x100 + HEADER_LEN], 3);
+    }
+
+    #[test]
+    fn survives_a_torn_save() {
+        let flash = RamFlash::new(0x200);
+        let blob = Blob::new(flash.clone(), COPIES);
+        blob.save(b"first").unwrap();
+        blob.save(b"second").unwrap();
+        flash.borrow_mut().power_left = Some(HEADER_LEN + 2);
+        assert!(blob.save(b"third").is_err());
+        flash.borrow_mut().power_left = None;
+        assert_eq!(blob.load().as_deref(), Some(&b"second"[..]));
+        // and the next save doesn't clobber the good copy either
+        blob.save(b"fourth").unwrap();
+        assert_eq!(blob.load().as_deref(), Some(&b"fourth"[..]));
+        assert_eq!(&flash.borrow().data[0x100 + HEADER_LEN..][..6], b"second");
+    }
+
+    fn record(n: u8) -> Vec<u8> {
+        vec![n; 10]
+    }
+
+    fn reopen(flash: &Rc<RefCell<RamFlash>>) -> Vec<Vec<u8>> {
+        Journal::open(flash.clone(), SECTORS).1
+    }
+
+    #[test]
+    fn journals_until_a_sector_fills() {
+        let flash = RamFlash::new(0x300);
+        let (mut journal, records) = Journal::open(flash.clone(), SECTORS);
+        assert!(records.is_empty());
+        for n in 0..12 {
+            journal.append(&record(n), || vec![record(n)]).unwrap();
+        }
+        assert_eq!(reopen(&flash), (0..12).map(record).collect::<Vec<_>>());
+        assert_eq!(flash.borrow().erases, 1);
+        // the next one doesn't fit, so the snapshot starts a new sector
+        journal.append(&record(12), || vec![record(11), record(12)]).unwrap();
+        assert_eq!(reopen(&flash), [record(11), record(12)]);
+        assert_eq!(flash.borrow().erases, 2);
+    }
+
+    #[test]
+    fn wraps_around_the_region() {
+        let flash = RamFlash::new(0x300);
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        for n in 0..100 {
+            journal.append(&record(n), || vec![record(n)]).unwrap();
+            assert_eq!(reopen(&flash).last(), Some(&record(n)));
+        }
+        // a sector for the first record, then one for each twelve after
+        assert_eq!(flash.borrow().erases, 9);
+        // and picking up where it left off after a restart
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        journal.append(&record(100), || vec![record(100)]).unwrap();
+        assert_eq!(reopen(&flash).len(), 5);
+    }
+
+    #[test]
+    fn keeps_the_old_sector_if_a_rotation_is_cut_off() {
+        let flash = RamFlash::new(0x300);
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        for n in 0..12 {
+            journal.append(&record(n), || vec![record(n)]).unwrap();
+        }
+        // the snapshot gets written, but not the header that vouches for it
+        flash.borrow_mut().power_left = Some(20);
+        assert!(journal.append(&record(12), || vec![record(12)]).is_err());
+        flash.borrow_mut().power_left = None;
+        assert_eq!(reopen(&flash), (0..12).map(record).collect::<Vec<_>>());
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        journal.append(&record(12), || vec![record(12)]).unwrap();
+        assert_eq!(reopen(&flash), [record(12)]);
+    }
+
+    #[test]
+    fn drops_a_torn_last_record() {
+        let flash = RamFlash::new(0x300);
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        for n in 0..3 {
+            journal.append(&record(n), || vec![record(n)]).unwrap();
+        }
+        flash.borrow_mut().power_left = Some(12);
+        assert!(journal.append(&record(3), || vec![record(3)]).is_err());
+        flash.borrow_mut().power_left = None;
+        let (mut journal, records) = Journal::open(flash.clone(), SECTORS);
+        assert_eq!(records, (0..3).map(record).collect::<Vec<_>>());
+        // nothing more goes after it, so the next record starts afresh
+        journal.append(&record(3), || vec![record(2), record(3)]).unwrap();
+        assert_eq!(reopen(&flash), [record(2), record(3)]);
+    }
+
+    #[test]
+    fn drops_a_torn_record_length() {
+        let flash = RamFlash::new(0x300);
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        for n in 0..2 {
+            journal.append(&record(n), || vec![record(n)]).unwrap();
+        }
+        // a length with most of its bits still set, as if cut off
+        let mut encoder = Encoder::default();
+        encoder.u32(0xffff_fff9);
+        encoder.u32(0);
+        let end = SECTOR_HEADER_LEN + 2 * Sectors::<RamFlash>::padded_len(10);
+        flash.borrow_mut().store(end, &encoder.finish()).unwrap();
+        let (mut journal, records) = Journal::open(flash.clone(), SECTORS);
+        assert_eq!(records, [record(0), record(1)]);
+        journal.append(&record(2), || vec![record(1), record(2)]).unwrap();
+        assert_eq!(reopen(&flash), [record(1), record(2)]);
+    }
+
+    #[test]
+    fn refuses_a_snapshot_too_big_for_a_sector() {
+        let flash = RamFlash::new(0x300);
+        let (mut journal, _) = Journal::open(flash.clone(), SECTORS);
+        for n in 0..12 {
+            journal.append(&record(n), || vec![record(n)]).unwrap();
+        }
+        for _ in 0..2 {
+            assert!(journal.append(&record(12), || (0..13).map(record).collect()).is_err());
+        }
+        // without erasing anything, so the old records are still there
+        assert_eq!(flash.borrow().erases, 1);
+        assert_eq!(reopen(&flash), (0..12).map(record).collect::<Vec<_>>());
+    }
+
+    fn logged(flash: &Rc<RefCell<RamFlash>>) -> Vec<Vec<u8>> {
+        Log::open(flash.clone(), SECTORS).records().collect()
+    }
+
+    #[test]
+    fn logs_across_sectors_in_order() {
+        let flash = RamFlash::new(0x300);
+        let mut log = Log::open(flash.clone(), SECTORS);
+        assert_eq!(log.records().count(), 0);
+        for n in 0..20 {
+            log.append(&record(n)).unwrap();
+        }
+        // and carries on after a restart
+        let mut log = Log::open(flash.clone(), SECTORS);
+        for n in 20..30 {
+            log.append(&record(n)).unwrap();
+        }
+        assert_eq!(logged(&flash), (0..30).map(record).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn makes_room_by_dropping_the_oldest_sector() {
+        let flash = RamFlash::new(0x300);
+        let mut log = Log::open(flash.clone(), SECTORS);
+        for n in 0..40 {
+            log.append(&record(n)).unwrap();
+        }
+        assert_eq!(logged(&flash), (12..40).map(record).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn logs_past_a_torn_record() {
+        let flash = RamFlash::new(0x300);
+        let mut log = Log::open(flash.clone(), SECTORS);
+        for n in 0..3 {
+            log.append(&record(n)).unwrap();
+        }
+        flash.borrow_mut().power_left = Some(12);
+        assert!(log.append(&record(3)).is_err());
+        flash.borrow_mut().power_left = None;
+        assert_eq!(logged(&flash), (0..3).map(record).collect::<Vec<_>>());
+        // the torn one's sector is done with, so it goes in the next
+        let mut log = Log::open(flash.clone(), SECTORS);
+        log.append(&record(3)).unwrap();
+        assert_eq!(logged(&flash), (0..4).map(record).collect::<Vec<_>>());
+    }
+}
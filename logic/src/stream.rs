@@ -0,0 +1,102 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Continuous weight logging over serial, for watching things slowly
+//! lose (or gain) weight from a laptop.
+
+use alloc::format;
+use alloc::string::String;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamFormat {
+    Csv,
+    Json,
+}
+
+pub const CSV_HEADER: &str = "t_ms,raw,weight,filtered";
+
+/// One reading off the load cell, as it gets logged.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub raw: i32,
+    /// Both in kg.
+    pub weight: f32,
+    pub filtered: f32,
+}
+
+pub struct Stream {
+    format: StreamFormat,
+    period_ms: u64,
+    next_ms: u64,
+}
+
+impl Stream {
+    pub fn new(format: StreamFormat, period_ms: u64) -> Stream {
+        Stream { format, period_ms, next_ms: 0 }
+    }
+
+    /// The line to print before any readings, if the format has one.
+    pub fn header(&self) -> Option<&'static str> {
+        match self.format {
+            StreamFormat::Csv => Some(CSV_HEADER),
+            StreamFormat::Json => None,
+        }
+    }
+
+    /// Returns the line to print for this reading, if it's time for
+    /// another one.
+    pub fn poll(&mut self, now_ms: u64, reading: &Sample) -> Option<String> {
+        if now_ms < self.next_ms {
+            return None;
+        }
+        // keep to the cadence, but skip ahead rather than bursting if
+        // the main loop stalled for a whole period
+        self.next_ms = if now_ms - self.next_ms >= self.period_ms {
+            now_ms + self.period_ms
+        } else {
+            self.next_ms + self.period_ms
+        };
+        Some(match self.format {
+            StreamFormat::Csv =>
+                format!("{},{},{:.5},{:.5}", now_ms, reading.raw, reading.weight, reading.filtered),
+            StreamFormat::Json =>
+                format!(
+                    "{{\"t_ms\":{},\"raw\":{},\"weight\":{:.5},\"filtered\":{:.5}}}",
+                    now_ms, reading.raw, reading.weight, reading.filtered,
+                ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: Sample = Sample { raw: 1234, weight: 0.5, filtered: 0.49 };
+
+    #[test]
+    fn keeps_the_cadence() {
+        let mut stream = Stream::new(StreamFormat::Csv, 100);
+        assert_eq!(stream.header(), Some(CSV_HEADER));
+        assert_eq!(stream.poll(1000, &SAMPLE).as_deref(), Some("1000,1234,0.50000,0.49000"));
+        assert_eq!(stream.poll(1050, &SAMPLE), None);
+        // a bit late, but it keeps to the original beat
+        assert!(stream.poll(1120, &SAMPLE).is_some());
+        assert_eq!(stream.poll(1199, &SAMPLE), None);
+        assert!(stream.poll(1200, &SAMPLE).is_some());
+        // a stall skips ahead instead of catching up all at once
+        assert!(stream.poll(1650, &SAMPLE).is_some());
+        assert_eq!(stream.poll(1700, &SAMPLE), None);
+        assert!(stream.poll(1750, &SAMPLE).is_some());
+    }
+
+    #[test]
+    fn prints_json() {
+        let mut stream = Stream::new(StreamFormat::Json, 100);
+        assert_eq!(stream.header(), None);
+        assert_eq!(
+            stream.poll(5, &SAMPLE).as_deref(),
+            Some("{\"t_ms\":5,\"raw\":1234,\"weight\":0.50000,\"filtered\":0.49000}"),
+        );
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Named countdown timers. They go by the clock rather than by how
+//! often anyone checks on them, and finished ones stay around until
+//! they're dismissed.
+
+use alloc::vec::Vec;
+
+pub struct Timer<N> {
+    pub id: i32,
+    pub name: N,
+    ends_ms: u64,
+    rung: bool,
+}
+
+impl<N> Timer<N> {
+    /// Rounded up, so it only reads 0 once it's really done.
+    pub fn seconds_left(&self, now_ms: u64) -> u32 {
+        self.ends_ms.saturating_sub(now_ms).div_ceil(1000) as u32
+    }
+
+    pub fn is_done(&self, now_ms: u64) -> bool {
+        now_ms >= self.ends_ms
+    }
+}
+
+pub struct Timers<N> {
+    timers: Vec<Timer<N>>,
+    next_id: i32,
+}
+
+impl<N> Default for Timers<N> {
+    fn default() -> Timers<N> {
+        Timers { timers: Vec::new(), next_id: 0 }
+    }
+}
+
+impl<N> Timers<N> {
+    /// Starts a new timer, returning its id.
+    pub fn start(&mut self, name: N, seconds: u32, now_ms: u64) -> i32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timers.push(Timer { id, name, ends_ms: now_ms + seconds as u64 * 1000, rung: false });
+        id
+    }
+
+    /// Stops a timer early, or dismisses a finished one.
+    pub fn cancel(&mut self, id: i32) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Timer<N>> {
+        self.timers.iter().find(|timer| timer.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Timer<N>> {
+        self.timers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// How many timers have finished since the last poll, so each one
+    /// only gets announced once.
+    pub fn poll(&mut self, now_ms: u64) -> usize {
+        let mut finished = 0;
+        for timer in self.timers.iter_mut().filter(|timer| !timer.rung && timer.is_done(now_ms)) {
+            timer.rung = true;
+            finished += 1;
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_down_by_the_clock() {
+        let mut timers = Timers::default();
+        let id = timers.start("rest", 30 * 60, 5_000);
+        let timer = timers.get(id).unwrap();
+        assert_eq!(timer.seconds_left(5_000), 1800);
+        assert_eq!(timer.seconds_left(5_001), 1800);
+        assert_eq!(timer.seconds_left(6_000), 1799);
+        assert_eq!(timer.seconds_left(5_000 + 1_799_001), 1);
+        assert!(!timer.is_done(5_000 + 1_799_999));
+        assert_eq!(timer.seconds_left(5_000 + 1_800_000), 0);
+        assert!(timer.is_done(5_000 + 1_800_000));
+        assert_eq!(timer.seconds_left(u64::MAX), 0);
+    }
+
+    #[test]
+    fn rings_once_however_often_polled() {
+        let mut timers = Timers::default();
+        timers.start("proof", 2, 0);
+        timers.start("bake", 5, 0);
+        assert_eq!(timers.poll(0), 0);
+        assert_eq!(timers.poll(1_999), 0);
+        // polled late, but it still only rings the once
+        assert_eq!(timers.poll(3_000), 1);
+        assert_eq!(timers.poll(3_001), 0);
+        assert_eq!(timers.poll(10_000), 1);
+        assert_eq!(timers.poll(20_000), 0);
+    }
+
+    #[test]
+    fn both_finishing_together_ring_together() {
+        let mut timers = Timers::default();
+        timers.start("a", 1, 0);
+        timers.start("b", 1, 0);
+        assert_eq!(timers.poll(1_000), 2);
+    }
+
+    #[test]
+    fn finished_timers_stay_until_dismissed() {
+        let mut timers = Timers::default();
+        let id = timers.start("rest", 1, 0);
+        timers.poll(2_000);
+        assert_eq!(timers.len(), 1);
+        timers.cancel(id);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn cancel_only_that_one() {
+        let mut timers = Timers::default();
+        let a = timers.start("a", 10, 0);
+        let b = timers.start("b", 10, 0);
+        timers.cancel(a);
+        assert!(timers.get(a).is_none());
+        assert_eq!(timers.get(b).unwrap().name, "b");
+        // cancelled ones never ring
+        assert_eq!(timers.poll(20_000), 1);
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut timers = Timers::default();
+        let a = timers.start("a", 10, 0);
+        timers.cancel(a);
+        let b = timers.start("b", 10, 0);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! How close to its target an ingredient has to get to count.
+//!
+//! Everything here is in kg, like the rest of the firmware.
+//! Tolerances are either side of the target, so ±.
+
+/// Going by the target, how much either side of it is close enough:
+/// up to each bound, the tolerance next to it.
+const DEFAULTS: [(f32, f32); 4] = [
+    (0.005, 0.0002),
+    (0.020, 0.0005),
+    (0.100, 0.001),
+    (0.500, 0.002),
+];
+
+/// Past the last bound above, a fraction of the target instead.
+const DEFAULT_FRACTION: f32 = 0.005;
+
+/// The tolerance for a target when the ingredient doesn't set its own.
+pub fn default_for(target: f32) -> f32 {
+    DEFAULTS.iter()
+        .find(|(bound, _)| target < *bound)
+        .map_or(target * DEFAULT_FRACTION, |(_, tolerance)| *tolerance)
+}
+
+/// An ingredient's own tolerance if it has one (anything above zero),
+/// otherwise the default for its target.
+pub fn for_target(target: f32, own: f32) -> f32 {
+    if own > 0. { own } else { default_for(target) }
+}
+
+/// How far past an edge an amount has to get before it counts as
+/// having crossed it, as a fraction of the tolerance, so load cell
+/// noise right at one doesn't flip back and forth.
+const DEADBAND: f32 = 0.25;
+/// But never less than this, for the tightest tolerances.
+const MIN_DEADBAND: f32 = 0.0001;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Band {
+    #[default]
+    Under,
+    InRange,
+    Over,
+}
+
+/// Where an amount sits relative to its target.
+pub fn band(target: f32, actual: f32, tolerance: f32) -> Band {
+    if actual < target - tolerance {
+        Band::Under
+    } else if actual > target + tolerance {
+        Band::Over
+    } else {
+        Band::InRange
+    }
+}
+
+/// Like `band`, but only moving on from `last` once the amount's
+/// clearly past the edge.
+pub fn settled_band(target: f32, actual: f32, tolerance: f32, last: Band) -> Band {
+    let deadband = (tolerance * DEADBAND).max(MIN_DEADBAND);
+    let held_back = match band(target, actual, tolerance).cmp(&last) {
+        core::cmp::Ordering::Greater => actual - deadband,
+        core::cmp::Ordering::Less => actual + deadband,
+        core::cmp::Ordering::Equal => return last,
+    };
+    band(target, held_back, tolerance)
+}
+
+/// How one pour's going: the band it's settled in, and which cues it's
+/// had, so each one only sounds once however much it wobbles.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pour {
+    pub band: Band,
+    pub cued_in_range: bool,
+    pub cued_over: bool,
+}
+
+impl Pour {
+    /// Takes a new reading, returning the band to cue if the pour's
+    /// just got there for the first time.
+    pub fn step(&mut self, target: f32, actual: f32, tolerance: f32) -> Option<Band> {
+        self.band = settled_band(target, actual, tolerance, self.band);
+        match self.band {
+            Band::InRange if !self.cued_in_range => {
+                self.cued_in_range = true;
+                Some(Band::InRange)
+            },
+            Band::Over if !self.cued_over => {
+                self.cued_over = true;
+                Some(Band::Over)
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn defaults_grow_with_the_target() {
+        assert_eq!(default_for(0.003), 0.0002);
+        assert_eq!(default_for(0.010), 0.0005);
+        assert_eq!(default_for(0.050), 0.001);
+        assert_eq!(default_for(0.128), 0.002);
+        assert_eq!(default_for(1.), 0.005);
+        let mut last = 0.;
+        for grams in 1..2000 {
+            let tolerance = default_for(grams as f32 / 1000.);
+            assert!(tolerance >= last);
+            last = tolerance;
+        }
+    }
+
+    #[test]
+    fn own_tolerance_wins() {
+        assert_eq!(for_target(0.128, 0.010), 0.010);
+        assert_eq!(for_target(0.128, 0.), 0.002);
+    }
+
+    #[test]
+    fn bands() {
+        assert_eq!(band(0.128, 0.100, 0.002), Band::Under);
+        assert_eq!(band(0.128, 0.1265, 0.002), Band::InRange);
+        assert_eq!(band(0.128, 0.128, 0.002), Band::InRange);
+        assert_eq!(band(0.128, 0.1295, 0.002), Band::InRange);
+        assert_eq!(band(0.128, 0.131, 0.002), Band::Over);
+    }
+
+    #[test]
+    fn holds_a_band_near_the_edge() {
+        // in range up to 130g, with half a gram of deadband
+        assert_eq!(settled_band(0.128, 0.1302, 0.002, Band::InRange), Band::InRange);
+        assert_eq!(settled_band(0.128, 0.1306, 0.002, Band::InRange), Band::Over);
+        assert_eq!(settled_band(0.128, 0.1298, 0.002, Band::Over), Band::Over);
+        assert_eq!(settled_band(0.128, 0.1294, 0.002, Band::Over), Band::InRange);
+        // a big jump goes as far as it clearly got
+        assert_eq!(settled_band(0.128, 0.140, 0.002, Band::Under), Band::Over);
+        assert_eq!(settled_band(0.128, 0.1258, 0.002, Band::Under), Band::Under);
+    }
+
+    #[test]
+    fn cues_each_band_once() {
+        let mut pour = Pour::default();
+        let cues: Vec<Option<Band>> = [0.100, 0.1265, 0.1255, 0.1265, 0.131, 0.129, 0.131]
+            .iter()
+            .map(|&actual| pour.step(0.128, actual, 0.002))
+            .collect();
+        assert_eq!(cues, [None, Some(Band::InRange), None, None, Some(Band::Over), None, None]);
+        assert_eq!(pour.band, Band::Over);
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! How fast the weight's changing, e.g. a sauce reducing, and so how
+//! long until it gets somewhere.
+
+use alloc::collections::VecDeque;
+
+/// How often to take a sample. Things reducing or drying change over
+/// minutes, so there's no point looking more often than this, and the
+/// samples in between are mostly bubbling and steam.
+pub const SAMPLE_MS: u64 = 5000;
+/// How many samples to fit a line through: the last five minutes.
+const WINDOW: usize = 60;
+/// How many it takes before the rate's worth anything.
+const MIN_SAMPLES: usize = 6;
+
+/// The rate of change of the weight, as a straight line through the
+/// last few minutes of it.
+#[derive(Default)]
+pub struct Trend {
+    // (ms, kg)
+    samples: VecDeque<(u64, f32)>,
+}
+
+impl Trend {
+    /// Returns the rate, in kg a second, every time there's a new one.
+    pub fn step(&mut self, weight: f32, now_ms: u64) -> Option<f32> {
+        if self.samples.back().is_some_and(|&(then_ms, _)| now_ms.saturating_sub(then_ms) < SAMPLE_MS) {
+            return None;
+        }
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now_ms, weight));
+        self.rate()
+    }
+
+    /// The least squares slope through the samples.
+    pub fn rate(&self) -> Option<f32> {
+        if self.samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let &(start_ms, _) = self.samples.front()?;
+        let n = self.samples.len() as f32;
+        let seconds = |ms: u64| (ms - start_ms) as f32 / 1000.;
+        let mean_t = self.samples.iter().map(|&(ms, _)| seconds(ms)).sum::<f32>() / n;
+        let mean_w = self.samples.iter().map(|&(_, w)| w).sum::<f32>() / n;
+        let (cov, var) = self.samples.iter().fold((0., 0.), |(cov, var), &(ms, w)| {
+            let dt = seconds(ms) - mean_t;
+            (cov + dt * (w - mean_w), var + dt * dt)
+        });
+        Some(cov / var)
+    }
+}
+
+/// How many seconds until the weight gets from `current` to `target` at
+/// `rate` kg a second, or `None` if it isn't heading that way.
+pub fn seconds_to(current: f32, target: f32, rate: f32) -> Option<u32> {
+    let seconds = (target - current) / rate;
+    if rate == 0. || seconds < 0. {
+        return None;
+    }
+    Some((seconds + 0.5) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_the_rate() {
+        let mut trend = Trend::default();
+        let mut rate = None;
+        // losing a gram every 10s
+        for sec in 0..60 {
+            if let Some(new) = trend.step(0.5 - sec as f32 * 0.0001, sec * 1000) {
+                rate = Some(new);
+            }
+        }
+        assert!((rate.unwrap() + 0.0001).abs() < 1e-6);
+    }
+
+    #[test]
+    fn needs_a_few_samples() {
+        let mut trend = Trend::default();
+        for sample in 0..5 {
+            assert_eq!(trend.step(0.5, sample * SAMPLE_MS), None);
+        }
+        assert_eq!(trend.step(0.5, 5 * SAMPLE_MS), Some(0.));
+    }
+
+    #[test]
+    fn forgets_old_samples() {
+        let mut trend = Trend::default();
+        for sample in 0..WINDOW as u64 {
+            trend.step(1.0, sample * SAMPLE_MS);
+        }
+        // then it starts dropping fast
+        let mut rate = None;
+        for sample in WINDOW as u64..2 * WINDOW as u64 {
+            rate = trend.step(1.0 - (sample - WINDOW as u64) as f32 * 0.001, sample * SAMPLE_MS);
+        }
+        assert!((rate.unwrap() + 0.0002).abs() < 1e-6);
+    }
+
+    #[test]
+    fn time_to_target() {
+        assert_eq!(seconds_to(0.5, 0.3, -0.001), Some(200));
+        assert_eq!(seconds_to(0.3, 0.5, 0.001), Some(200));
+        // heading the wrong way, or not at all
+        assert_eq!(seconds_to(0.5, 0.3, 0.001), None);
+        assert_eq!(seconds_to(0.5, 0.3, 0.), None);
+    }
+}
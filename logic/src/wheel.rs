@@ -0,0 +1,73 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Encoder acceleration: spinning the wheel quickly moves further per
+//! detent, so long lists and big numbers don't take forever to get
+//! through.
+
+/// Detents further apart than this are just turning, not spinning.
+const SLOW_US: u64 = 150_000;
+
+/// How many steps a detent is worth for how far apart they're coming,
+/// quickest first. Anything slower than all of them is one step.
+pub type Curve = [(u64, i32)];
+
+/// For lists, which shouldn't run away from you.
+pub const LIST: &Curve = &[(20_000, 4), (40_000, 2)];
+/// For numbers, where you'd rather overshoot and come back.
+pub const VALUE: &Curve = &[(20_000, 10), (35_000, 5), (60_000, 2)];
+
+#[derive(Debug, Default)]
+pub struct Wheel {
+    // direction and when, for the last detent
+    last: Option<(bool, u64)>,
+    interval_us: u64,
+}
+
+impl Wheel {
+    /// Notes a detent, returning how far apart they've been coming
+    /// lately, in µs. Turning back the other way starts over.
+    pub fn turn(&mut self, clockwise: bool, at_us: u64) -> u64 {
+        let interval = match self.last {
+            Some((was_clockwise, then_us)) if was_clockwise == clockwise => at_us.saturating_sub(then_us),
+            _ => SLOW_US,
+        };
+        self.last = Some((clockwise, at_us));
+        // averaged, so one quick pair of detents doesn't count as a spin
+        self.interval_us = if interval >= SLOW_US { SLOW_US } else { (self.interval_us + interval) / 2 };
+        self.interval_us
+    }
+}
+
+pub fn steps(curve: &Curve, interval_us: u64) -> i32 {
+    curve.iter()
+        .find(|&&(under_us, _)| interval_us < under_us)
+        .map_or(1, |&(_, steps)| steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speeds_up() {
+        let mut wheel = Wheel::default();
+        let mut at_us = 0;
+        let mut spin = |wheel: &mut Wheel, clockwise, gap_us| {
+            at_us += gap_us;
+            wheel.turn(clockwise, at_us)
+        };
+        assert_eq!(steps(VALUE, spin(&mut wheel, true, 1_000_000)), 1);
+        // it takes a few quick ones to get going
+        assert_eq!(steps(VALUE, spin(&mut wheel, true, 10_000)), 1);
+        assert_eq!(steps(VALUE, spin(&mut wheel, true, 10_000)), 2);
+        assert_eq!(steps(VALUE, spin(&mut wheel, true, 10_000)), 5);
+        let interval = spin(&mut wheel, true, 10_000);
+        assert_eq!((steps(LIST, interval), steps(VALUE, interval)), (4, 10));
+        // back the other way to fine tune
+        assert_eq!(steps(VALUE, spin(&mut wheel, false, 10_000)), 1);
+        // and pausing starts over too
+        spin(&mut wheel, false, 10_000);
+        assert_eq!(steps(VALUE, spin(&mut wheel, false, 200_000)), 1);
+    }
+}
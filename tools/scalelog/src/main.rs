@@ -0,0 +1,138 @@
+// Copyright (C) Jessie Grosen 2024
+// SPDX-License-Identifier: MIT
+
+//! Talks to the scale's serial port to record its weight stream to a
+//! file, or to play a recording back into its fake load cell.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: scalelog record <port> <file> [csv|json] [period_ms]
+       scalelog replay <port> <file> [speed]";
+
+fn open_port(path: &str) -> io::Result<File> {
+    let port = OpenOptions::new().read(true).write(true).open(path)?;
+    // `stty -F` vs `stty -f` differs between Linux and macOS, but
+    // both take the device on stdin
+    let status = Command::new("stty")
+        .args(["raw", "-echo"])
+        .stdin(Stdio::from(port.try_clone()?))
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+    Ok(port)
+}
+
+/// The device also prints debug output, so only keep the lines that
+/// belong to the stream.
+fn is_stream_line(line: &str, format: &str) -> bool {
+    match format {
+        "json" => line.starts_with('{'),
+        _ => line.starts_with("t_ms,") || line.starts_with(|c: char| c.is_ascii_digit()),
+    }
+}
+
+fn record(port: &str, path: &str, format: &str, period_ms: u64) -> io::Result<()> {
+    let mut port = open_port(port)?;
+    let mut out = File::create(path)?;
+    writeln!(port, "stream {} {}", format, period_ms)?;
+    eprintln!("recording to {}, ^C to stop", path);
+    let mut count = 0u64;
+    for line in BufReader::new(port.try_clone()?).lines() {
+        let line = line?;
+        let line = line.trim_end();
+        if is_stream_line(line, format) {
+            writeln!(out, "{}", line)?;
+            out.flush()?;
+            count += 1;
+            if count.is_multiple_of(100) {
+                eprintln!("{} readings", count);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pulls `"key":value` out of a JSON line without bothering with a
+/// real parser, since we know exactly what the firmware prints.
+fn json_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("\"{}\":", key))? + key.len() + 3;
+    let rest = &line[start..];
+    let end = rest.find([',', '}']).unwrap_or(rest.len());
+    Some(rest[..end].trim())
+}
+
+/// Parses a recording into (timestamp in ms, raw ADC value) pairs.
+fn parse_recording(path: &str) -> io::Result<Vec<(u64, i32)>> {
+    let bad = |n: usize| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: bad reading", path, n + 1));
+    let mut readings = Vec::new();
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("t_ms,") {
+            continue;
+        }
+        let (t_ms, raw) = if line.starts_with('{') {
+            (json_field(line, "t_ms"), json_field(line, "raw"))
+        } else {
+            let mut fields = line.split(',');
+            (fields.next(), fields.next())
+        };
+        let t_ms = t_ms.and_then(|t| t.parse().ok()).ok_or_else(|| bad(n))?;
+        let raw = raw.and_then(|r| r.parse().ok()).ok_or_else(|| bad(n))?;
+        readings.push((t_ms, raw));
+    }
+    Ok(readings)
+}
+
+fn replay(port: &str, path: &str, speed: f64) -> io::Result<()> {
+    let readings = parse_recording(path)?;
+    let mut port = open_port(port)?;
+    eprintln!("replaying {} readings", readings.len());
+    let mut prev_ms = readings.first().map_or(0, |&(t_ms, _)| t_ms);
+    for (t_ms, raw) in readings {
+        let wait_ms = t_ms.saturating_sub(prev_ms) as f64 / speed;
+        thread::sleep(Duration::from_secs_f64(wait_ms / 1000.));
+        prev_ms = t_ms;
+        writeln!(port, "fake {}", raw)?;
+    }
+    writeln!(port, "fake off")?;
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        ["record", port, path] =>
+            record(port, path, "csv", 100),
+        ["record", port, path, format @ ("csv" | "json")] =>
+            record(port, path, format, 100),
+        ["record", port, path, format @ ("csv" | "json"), period_ms] =>
+            match period_ms.parse() {
+                Ok(period_ms) => record(port, path, format, period_ms),
+                Err(_) => Err(io::Error::other("bad period")),
+            },
+        ["replay", port, path] =>
+            replay(port, path, 1.),
+        ["replay", port, path, speed] =>
+            match speed.parse() {
+                Ok(speed) if speed > 0. => replay(port, path, speed),
+                _ => Err(io::Error::other("bad speed")),
+            },
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        },
+    };
+    if let Err(err) = result {
+        eprintln!("scalelog: {}", err);
+        process::exit(1);
+    }
+}